@@ -0,0 +1,64 @@
+extern crate grid_pathfinding;
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use grid_pathfinding::{find_path_impl, GridMap, load_movingai_map, load_movingai_scenarios, path_length};
+
+/// Runs every scenario of a MovingAI `.scen` file and compares the length of the path found to the
+/// optimal length of the scenario.
+///
+/// The scenario file defaults to the bundled sample and can be changed with the `MOVINGAI_SCEN`
+/// environment variable. Maps are looked up in the directory of the scenario file.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let scen_path = env::var_os("MOVINGAI_SCEN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/data/sample.map.scen"));
+    let dir = scen_path.parent().unwrap_or_else(|| Path::new("."));
+    let scenarios = load_movingai_scenarios(&scen_path)?;
+
+    let mut maps: HashMap<PathBuf, GridMap> = HashMap::new();
+    let mut total_time = Duration::default();
+    let mut ratios = Vec::with_capacity(scenarios.len());
+    let mut failures = 0;
+
+    println!("bucket\tstart\tgoal\toptimal\tlength\tratio\ttime_us");
+    for scenario in scenarios.iter() {
+        let map_path = dir.join(scenario.map.file_name().unwrap_or_default());
+        if !maps.contains_key(&map_path) {
+            maps.insert(map_path.clone(), load_movingai_map(&map_path)?);
+        }
+        let map = &maps[&map_path];
+
+        let now = Instant::now();
+        let result = find_path_impl(map, scenario.start, scenario.goal);
+        let elapsed = now.elapsed();
+        total_time += elapsed;
+
+        let length = match result {
+            Ok(path) => path_length(&path),
+            Err(_) => {
+                failures += 1;
+                println!("{}\t{:?}\t{:?}\t{:.3}\tno path", scenario.bucket, scenario.start, scenario.goal, scenario.optimal_length);
+                continue;
+            }
+        };
+        let ratio = if scenario.optimal_length > 0. { length / scenario.optimal_length } else { 1. };
+        ratios.push(ratio);
+
+        println!(
+            "{}\t{:?}\t{:?}\t{:.3}\t{:.3}\t{:.3}\t{}",
+            scenario.bucket, scenario.start, scenario.goal, scenario.optimal_length, length, ratio, elapsed.as_micros(),
+        );
+    }
+
+    let mean_ratio = ratios.iter().sum::<f64>() / ratios.len().max(1) as f64;
+    let max_ratio = ratios.iter().cloned().fold(0., f64::max);
+    println!(
+        "{} scenarios, {} without path, mean ratio {:.3}, max ratio {:.3}, total time {:?}",
+        scenarios.len(), failures, mean_ratio, max_ratio, total_time,
+    );
+    Ok(())
+}
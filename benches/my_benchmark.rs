@@ -2,10 +2,9 @@ extern crate grid_pathfinding;
 
 
 use criterion::{black_box, Criterion, criterion_group, criterion_main};
-use ndarray::{Array2, ArrayView2};
-use orbclient::{Color, EventOption, Renderer, Window};
+use ndarray::Array2;
 
-use grid_pathfinding::{Grid, GridMap};
+use grid_pathfinding::{BitGrid, Grid, GridMap, LandmarkMap, Landmarks};
 
 fn make_wall(obstacles: &mut Array2<bool>, x: usize) {
     let (_, height) = obstacles.dim();
@@ -47,6 +46,28 @@ fn find_in_snail_map(c: &mut Criterion) {
             )
         })
     });
+
+    c.bench_function(format!("find bidirectional path in snail map {}x{}", WIDTH, HEIGHT).as_str(), |b| {
+        b.iter(|| {
+            grid_pathfinding::find_path_bidirectional(
+                black_box(&grid_map),
+                black_box(start),
+                black_box(end),
+            )
+        })
+    });
+
+    let landmarks = Landmarks::new(&grid_map, 4);
+    let guided_map = LandmarkMap::new(&grid_map, &landmarks);
+    c.bench_function(format!("find bidirectional path with landmarks in snail map {}x{}", WIDTH, HEIGHT).as_str(), |b| {
+        b.iter(|| {
+            grid_pathfinding::find_path_bidirectional(
+                black_box(&guided_map),
+                black_box(start),
+                black_box(end),
+            )
+        })
+    });
 }
 
 fn find_in_empty_map(c: &mut Criterion) {
@@ -69,6 +90,25 @@ fn find_in_empty_map(c: &mut Criterion) {
 }
 
 
+fn find_in_empty_bit_grid(c: &mut Criterion) {
+    const WIDTH: isize = 300;
+    const HEIGHT: isize = 200;
+    let start = (WIDTH - 1, HEIGHT - 1);
+    let end = (0, 0);
+    let bit_grid = BitGrid::new(((0, 0), (WIDTH, HEIGHT)));
+
+    c.bench_function(format!("find path in empty bit grid {}x{}", WIDTH, HEIGHT).as_str(), |b| {
+        b.iter(|| {
+            grid_pathfinding::find_path_impl(
+                black_box(&bit_grid),
+                black_box(start),
+                black_box(end),
+            )
+        })
+    });
+}
+
+
 fn find_in_map_with_one_big_obstacle(c: &mut Criterion) {
     const WIDTH: isize = 300;
     const HEIGHT: isize = 200;
@@ -107,48 +147,40 @@ fn find_in_map_with_one_big_obstacle(c: &mut Criterion) {
             assert_eq!(false, result.is_err());
         })
     });
-}
-
-criterion_group!(benches, find_in_empty_map, find_in_snail_map, find_in_map_with_one_big_obstacle);
-criterion_main!(benches);
-
-fn _draw_map((width, height): (u32, u32), obstacles: ArrayView2<bool>, path: &[(isize, isize)]) {
-    const CELL_SIZE: u32 = 4;
-    const CELL_SIZE_I32: i32 = CELL_SIZE as i32;
-    let mut window = Window::new(
-        0,
-        0,
-        width * CELL_SIZE,
-        height * CELL_SIZE,
-        "TITLE",
-    ).unwrap();
-
-    for ((x, y), val) in obstacles.indexed_iter() {
-        let (x, y) = (x as i32, y as i32);
-        if *val {
-            window.rect(x * CELL_SIZE_I32, y * CELL_SIZE_I32, CELL_SIZE, CELL_SIZE, Color::rgb(100, 100, 100));
-        }
-    }
 
-    for w in path.windows(2) {
-        let (prev, next) = (w[0], w[1]);
-        let (ax, ay) = (prev.0 as i32, prev.1 as i32);
-        window.rect(ax * CELL_SIZE_I32, ay * CELL_SIZE_I32, CELL_SIZE, CELL_SIZE, Color::rgb(255, 0, 0));
-
-        let (bx, by) = (next.0 as i32, next.1 as i32);
-        window.rect(bx * CELL_SIZE_I32, by * CELL_SIZE_I32, CELL_SIZE, CELL_SIZE, Color::rgb(255, 0, 0));
-
-        window.line(ax * CELL_SIZE_I32, ay * CELL_SIZE_I32, bx * CELL_SIZE_I32, by * CELL_SIZE_I32, Color::rgb(255, 0, 0))
-    }
+    let hpa = grid_pathfinding::Hpa::new(&grid_map, 16);
+    c.bench_function(format!("find HPA* path in map with one big obstacle {}x{}", WIDTH, HEIGHT).as_str(), |b| {
+        b.iter(|| {
+            let result = hpa.find_path(
+                black_box(&grid_map),
+                black_box(start),
+                black_box(end),
+            );
+            assert!(result.is_ok());
+        })
+    });
 
+    let bit_grid = BitGrid::from(grid_map.obstacles());
+    c.bench_function(format!("find JPS path in map with one big obstacle {}x{}", WIDTH, HEIGHT).as_str(), |b| {
+        b.iter(|| {
+            let result = grid_pathfinding::find_path_jps(
+                black_box(&bit_grid),
+                black_box(start),
+                black_box(end),
+            );
+            assert!(result.is_ok());
+        })
+    });
+}
 
-    window.sync();
+criterion_group!(benches, find_in_empty_map, find_in_empty_bit_grid, find_in_snail_map, find_in_map_with_one_big_obstacle);
+criterion_main!(benches);
 
-    'events: loop {
-        for event in window.events() {
-            if let EventOption::Quit(_quit_event) = event.to_option() {
-                break 'events;
-            }
-        }
-    }
+/// Writes a PNG image of the map with the cells explored by the search and the path found, which
+/// helps to see what a benchmark is actually doing.
+fn _save_search_image(filename: &str, grid_map: &GridMap, start: (isize, isize), end: (isize, isize)) {
+    let trace = grid_pathfinding::find_path_traced(grid_map, start, end);
+    let path = trace.path.unwrap_or_default();
+    let image = grid_pathfinding::render_png(grid_map, Some(&trace.explored), &path, 4).unwrap();
+    std::fs::write(filename, image).unwrap();
 }
@@ -2,35 +2,6 @@ from math import sqrt
 
 import grid_pathfinding
 import numpy as np
-from bresenham import bresenham
-
-
-def print_grid(grid, start, end, path):
-    hs = '\033[0;37;47m|\033[0;0m'
-    vs = '\033[0;37;47m-\033[0;0m'
-    cs = '\033[0;37;47m \033[0;0m'
-    separator = cs.join([vs * 3] * len(grid))
-    separator = f"{cs}{separator}{cs}"
-    to_print = ""
-    for y in range(len(grid[0]))[::-1]:
-        to_print += separator + "\n"
-        for x in range(len(grid)):
-            char = "   "
-            if (x, y) == start:
-                char = "\033[0;37;42m S \033[0;0m"
-            elif (x, y) == end:
-                char = "\033[0;37;42m E \033[0;0m"
-            elif (x, y) in path and grid[x, y]:
-                char = "%%%"
-            elif (x, y) in path:
-                char = "\033[0;37;42m   \033[0;0m"
-            elif grid[x, y]:
-                char = "\033[0;37;41m   \033[0;0m"
-
-            to_print += f"{hs}{char}"
-        to_print += f"{hs}\n"
-    to_print += separator + "\n"
-    print(to_print)
 
 
 if __name__ == '__main__':
@@ -44,17 +15,33 @@ if __name__ == '__main__':
 
     start = (0, 0)
     end = (9, 9)
-    print_grid(arr, start, end, [])
+    print(grid_pathfinding.render_map(arr, [], start, end))
 
     path = grid_pathfinding.find_path(arr, start, end)
-    print_grid(arr, start, end, path)
-
-    path2 = []
-    for i in range(1, len(path)):
-        prev = path[i - 1]
-        cur = path[i]
-        path2 += bresenham(*cur, *prev)
-    print_grid(arr, start, end, path2)
+    print(grid_pathfinding.render_map(arr, path, start, end))
 
     exit_point = grid_pathfinding.exit_red_zone(arr, (4,4))
-    print_grid(arr, (4,4), exit_point, [])
+    print(grid_pathfinding.render_map(arr, [], (4, 4), exit_point))
+
+    # Maps can also be given as a function, the search only calls it for the cells it explores.
+    path = grid_pathfinding.find_path_callback(lambda x, y: arr[x, y], start, end, ((0, 0), arr.shape))
+    print(grid_pathfinding.render_map(arr, path, start, end))
+
+    # A flow field gives the next step towards the goal from every cell, for many agents at once.
+    directions = grid_pathfinding.flow_field(arr, end)
+    cell = start
+    while cell != end:
+        dx, dy = directions[cell]
+        cell = (cell[0] + dx, cell[1] + dy)
+    print(grid_pathfinding.distance_field(arr, end)[start])
+
+    # The nearest of several goals can be reached with a single search.
+    goal, path = grid_pathfinding.find_path_to_nearest(arr, start, [(9, 0), (0, 9), (9, 9)])
+    print(grid_pathfinding.render_map(arr, path, start, goal))
+
+    # Paths from cell to adjacent cell can be smoothed.
+    print(grid_pathfinding.smooth_path(arr, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (6, 2)], string_pulling=True))
+
+    # Vehicles can follow a smooth trajectory through the waypoints instead.
+    trajectory = grid_pathfinding.spline_path(arr, path, step=0.25)
+    print(len(trajectory), trajectory[:3])
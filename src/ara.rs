@@ -0,0 +1,260 @@
+use crate::grid::Grid;
+use crate::heap::DecreaseKeyHeap;
+use crate::map::Map;
+use crate::point::Point2D;
+
+const DEFAULT_INITIAL_WEIGHT: f64 = 3.;
+const DEFAULT_WEIGHT_STEP: f64 = 0.5;
+
+/// A path found by `AraStar`, whose cost is at most `bound` times the cost of a shortest path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AraSolution {
+    pub path: Vec<Point2D>,
+    pub cost: f64,
+    pub bound: f64,
+}
+
+/// An Anytime Repairing A* search, which quickly finds a path with a heavily weighted heuristic,
+/// then improves it while time remains.
+///
+/// Each item of the iterator is a path at least as short as the previous one, found by a search
+/// with a smaller weight which reuses the previous searches. The iterator ends after a shortest
+/// path, with a bound of 1, or if there is no path. Callers with a deadline stop iterating when
+/// it is reached and keep the last solution:
+///
+/// ```ignore
+/// let mut best = None;
+/// for solution in AraStar::new(&map, start, goal) {
+///     best = Some(solution);
+///     if Instant::now() > deadline { break; }
+/// }
+/// ```
+///
+/// Paths go from cell to adjacent cell, as given by `Map::neighbors`.
+pub struct AraStar<'a, M: Map> {
+    map: &'a M,
+    start: Point2D,
+    goal: Point2D,
+    weight: f64,
+    weight_step: f64,
+    g_score: Grid<f64>,
+    came_from: Grid<Option<Point2D>>,
+    open_set: DecreaseKeyHeap<Point2D>,
+    closed: Grid<bool>,
+    /// Closed cells whose cost decreased during the current search, expanded by the next one.
+    inconsistent: Vec<Point2D>,
+    is_inconsistent: Grid<bool>,
+    searched: bool,
+    done: bool,
+}
+
+impl<'a, M: Map> AraStar<'a, M> {
+    pub fn new(map: &'a M, start: Point2D, goal: Point2D) -> AraStar<'a, M> {
+        let boundaries = map.boundaries();
+        let mut search = AraStar {
+            map,
+            start,
+            goal,
+            weight: DEFAULT_INITIAL_WEIGHT,
+            weight_step: DEFAULT_WEIGHT_STEP,
+            g_score: Grid::new(boundaries, f64::INFINITY),
+            came_from: Grid::new(boundaries, None),
+            open_set: DecreaseKeyHeap::new(),
+            closed: Grid::new(boundaries, false),
+            inconsistent: Vec::new(),
+            is_inconsistent: Grid::new(boundaries, false),
+            searched: false,
+            done: map.obstacle(&start) || map.obstacle(&goal),
+        };
+        search.g_score.set(&start, 0.);
+        search
+    }
+
+    /// Sets the weight of the heuristic of the first search, which is at least 1.
+    pub fn with_initial_weight(mut self, weight: f64) -> Self {
+        self.weight = weight.max(1.);
+        self
+    }
+
+    /// Sets by how much the weight decreases between two searches. With a step which is not
+    /// positive, the second search directly finds a shortest path.
+    pub fn with_weight_step(mut self, step: f64) -> Self {
+        self.weight_step = step;
+        self
+    }
+
+    pub fn start(&self) -> Point2D {
+        self.start
+    }
+
+    pub fn goal(&self) -> Point2D {
+        self.goal
+    }
+
+    /// Returns the weight of the heuristic of the last search.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn heuristic(&self, cell: &Point2D) -> f64 {
+        self.map.heuristic(cell, &self.goal)
+    }
+
+    fn f_score(&self, cell: &Point2D) -> f64 {
+        self.g_score.get(cell) + self.weight * self.heuristic(cell)
+    }
+
+    /// Lowers the weight and prepares the open set of the next search, made of the cells left
+    /// open and the inconsistent cells of the previous one.
+    fn tighten(&mut self) {
+        self.weight = match self.weight_step > 0. {
+            true => (self.weight - self.weight_step).max(1.),
+            false => 1.,
+        };
+
+        let mut cells = std::mem::take(&mut self.inconsistent);
+        for cell in cells.iter() {
+            self.is_inconsistent.set(cell, false);
+        }
+        while let Some(element) = self.open_set.pop() {
+            cells.push(element.position);
+        }
+        for cell in cells.iter() {
+            self.open_set.push(*cell, self.f_score(cell));
+        }
+        self.closed = Grid::new(self.map.boundaries(), false);
+    }
+
+    fn improve_path(&mut self) {
+        while let Some(top) = self.open_set.peek() {
+            if self.g_score.get(&self.goal) <= top.f_score { break; }
+
+            let position = self.open_set.pop().unwrap().position;
+            self.closed.set(&position, true);
+            let g = self.g_score.get(&position);
+
+            for neighbor in self.map.neighbors(&position) {
+                if self.map.obstacle(&neighbor) { continue; }
+
+                let tentative_g_score = g + self.map.distance(&position, &neighbor);
+                if tentative_g_score < self.g_score.get(&neighbor) {
+                    self.g_score.set(&neighbor, tentative_g_score);
+                    self.came_from.set(&neighbor, Some(position));
+                    if !self.closed.get(&neighbor) {
+                        self.open_set.push(neighbor, self.f_score(&neighbor));
+                    } else if !self.is_inconsistent.get(&neighbor) {
+                        self.is_inconsistent.set(&neighbor, true);
+                        self.inconsistent.push(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    fn build_path(&self) -> Vec<Point2D> {
+        let mut path = vec![self.goal];
+        let mut pos = self.goal;
+        while let Some(previous) = self.came_from.get(&pos) {
+            path.push(previous);
+            pos = previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl<M: Map> Iterator for AraStar<'_, M> {
+    type Item = AraSolution;
+
+    fn next(&mut self) -> Option<AraSolution> {
+        if self.done {
+            return None;
+        }
+        if self.searched {
+            self.tighten();
+        } else {
+            self.open_set.push(self.start, self.f_score(&self.start));
+            self.searched = true;
+        }
+        self.improve_path();
+
+        let cost = self.g_score.get(&self.goal);
+        if !cost.is_finite() {
+            self.done = true;
+            return None;
+        }
+
+        // No path is shorter than the smallest unweighted f score of the cells left to expand.
+        let lower_bound = self.open_set.positions()
+            .chain(self.inconsistent.iter().copied())
+            .map(|cell| self.g_score.get(&cell) + self.heuristic(&cell))
+            .fold(f64::INFINITY, f64::min);
+        let bound = self.weight.min((cost / lower_bound).max(1.));
+        self.done = bound <= 1.;
+
+        Some(AraSolution { path: self.build_path(), cost, bound })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::dstar_lite::DStarLite;
+
+    use super::*;
+
+    #[test]
+    fn test_improving_solutions() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S...#.........
+            ....#...####..
+            .##.#.#.#.....
+            ..#...#.#.###.
+            ..#####.#...#.
+            ........###.#G
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let shortest = DStarLite::new(&map, start, goal).plan(&map)?;
+        let shortest: f64 = shortest.windows(2).map(|w| map.distance(&w[0], &w[1])).sum();
+
+        let solutions: Vec<AraSolution> = AraStar::new(&map, start, goal).with_initial_weight(5.).collect();
+        assert!(!solutions.is_empty());
+        for solution in solutions.iter() {
+            assert_eq!(solution.path.first(), Some(&start));
+            assert_eq!(solution.path.last(), Some(&goal));
+            assert!(solution.cost <= solution.bound * shortest + 1e-9);
+        }
+        assert!(solutions.windows(2).all(|w| w[1].cost <= w[0].cost && w[1].bound <= w[0].bound));
+
+        let last = solutions.last().unwrap();
+        assert_eq!(last.bound, 1.);
+        assert!((last.cost - shortest).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_weight_step() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#...
+            ..#.#.
+            ....#G
+        ")?;
+        let solutions: Vec<AraSolution> = AraStar::new(&map, start.unwrap(), goal.unwrap())
+            .with_initial_weight(10.)
+            .with_weight_step(0.)
+            .collect();
+        assert!(!solutions.is_empty() && solutions.len() <= 2);
+        assert_eq!(solutions.last().map(|s| s.bound), Some(1.));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#..
+            ..#.G
+        ")?;
+        assert_eq!(AraStar::new(&map, start.unwrap(), goal.unwrap()).count(), 0);
+        Ok(())
+    }
+}
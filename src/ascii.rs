@@ -0,0 +1,163 @@
+use std::iter::once;
+
+use bresenham::Bresenham;
+use ndarray::Array2;
+
+use crate::errors::MapFormatError;
+use crate::grid::Grid;
+use crate::map::{Bounded, GridMap};
+use crate::point::{is_in_bounds, Point2D};
+
+const OBSTACLE: char = '#';
+const FREE: char = '.';
+const START: char = 'S';
+const GOAL: char = 'G';
+const PATH: char = '*';
+const PATH_IN_OBSTACLE: char = 'X';
+
+/// A map parsed from text, along with the start and goal markers it contains.
+pub struct AsciiMap {
+    pub map: GridMap,
+    pub start: Option<Point2D>,
+    pub goal: Option<Point2D>,
+}
+
+fn format_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("ascii: {}", msg)))
+}
+
+/// Parses a map drawn with `#` for obstacles, `.` for free cells and `S`/`G` for the start and
+/// goal cells.
+///
+/// The first line is the row with the highest `y`, the first column has `x = 0`. Blank lines and
+/// indentation are ignored so maps can be written inline in tests.
+pub fn parse_ascii(text: &str) -> Result<AsciiMap, Box<dyn std::error::Error>> {
+    let rows: Vec<Vec<char>> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect())
+        .collect();
+
+    let height = rows.len();
+    let width = rows.first().map(Vec::len).unwrap_or(0);
+    if width == 0 {
+        return Err(format_error("empty map"));
+    }
+
+    let mut obstacles = Array2::from_elem((width, height), false);
+    let mut start = None;
+    let mut goal = None;
+    for (row, chars) in rows.iter().enumerate() {
+        if chars.len() != width {
+            return Err(format_error(&format!("row {} has {} cells, expected {}", row, chars.len(), width)));
+        }
+        let y = height - 1 - row;
+        for (x, c) in chars.iter().enumerate() {
+            let position = (x as isize, y as isize);
+            match *c {
+                OBSTACLE => obstacles[(x, y)] = true,
+                FREE => {}
+                START if start.is_none() => start = Some(position),
+                GOAL if goal.is_none() => goal = Some(position),
+                START | GOAL => return Err(format_error(&format!("duplicated marker {:?}", c))),
+                _ => return Err(format_error(&format!("unexpected character {:?}", c))),
+            }
+        }
+    }
+
+    Ok(AsciiMap {
+        map: GridMap::new(Grid::from(obstacles)),
+        start,
+        goal,
+    })
+}
+
+impl GridMap {
+    pub fn from_ascii(text: &str) -> Result<GridMap, Box<dyn std::error::Error>> {
+        Ok(parse_ascii(text)?.map)
+    }
+}
+
+/// Draws the map as text, in the format read by `parse_ascii`, with the cells crossed by the path
+/// drawn as `*` (or `X` when the cell is an obstacle).
+pub fn render_ascii(map: &GridMap, start: Option<Point2D>, goal: Option<Point2D>, path: &[Point2D]) -> String {
+    let obstacles = map.obstacles();
+    let boundaries = map.boundaries();
+    let mut chars = obstacles.map(|obstacle| if obstacle { OBSTACLE } else { FREE });
+
+    let path_cells = path.windows(2)
+        .flat_map(|w| Bresenham::new(w[0], w[1]))
+        .chain(path.last().cloned());
+    for cell in path_cells.filter(|cell| is_in_bounds(*cell, boundaries)) {
+        let c = if obstacles.get(&cell) { PATH_IN_OBSTACLE } else { PATH };
+        chars.set(&cell, c);
+    }
+    for (cell, c) in once((start, START)).chain(once((goal, GOAL))) {
+        if let Some(cell) = cell.filter(|cell| is_in_bounds(*cell, boundaries)) {
+            chars.set(&cell, c);
+        }
+    }
+
+    let ((min_x, min_y), (max_x, max_y)) = boundaries;
+    let mut text = String::new();
+    for y in (min_y..max_y).rev() {
+        text.extend((min_x..max_x).map(|x| chars.get(&(x, y))));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Map;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S..#
+            .#.G
+        ")?;
+
+        assert_eq!(map.boundaries(), ((0, 0), (4, 2)));
+        assert_eq!(start, Some((0, 1)));
+        assert_eq!(goal, Some((3, 0)));
+        assert!(map.obstacle(&(3, 1)));
+        assert!(map.obstacle(&(1, 0)));
+        assert!(!map.obstacle(&(0, 0)));
+        assert!(!map.obstacle(&(3, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(GridMap::from_ascii("").is_err());
+        assert!(GridMap::from_ascii("..\n...").is_err());
+        assert!(GridMap::from_ascii("..\n.?").is_err());
+        assert!(GridMap::from_ascii("S.\n.S").is_err());
+    }
+
+    #[test]
+    fn test_render() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            .....
+            ..#..
+            .....
+        ")?;
+
+        let got = render_ascii(&map, Some((0, 0)), Some((4, 2)), &[(0, 0), (2, 2), (4, 2)]);
+        assert_eq!(got, "..**G\n.*#..\nS....\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let text = "S..#\n.#..\n...G\n";
+        let AsciiMap { map, start, goal } = parse_ascii(text)?;
+
+        assert_eq!(render_ascii(&map, start, goal, &[]), text);
+        Ok(())
+    }
+}
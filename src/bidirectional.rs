@@ -0,0 +1,183 @@
+use std::collections::BinaryHeap;
+
+use crate::errors::NoPathFoundError;
+use crate::grid::Grid;
+use crate::heap::HeapElement;
+use crate::map::Map;
+use crate::point::Point2D;
+
+/// One of the two searches of `find_path_bidirectional`, going from `source` towards `target`.
+struct Frontier {
+    target: Point2D,
+    open_set: BinaryHeap<HeapElement<Point2D>>,
+    g_score: Grid<f64>,
+    f_score: Grid<f64>,
+    came_from: Grid<Option<Point2D>>,
+}
+
+impl Frontier {
+    fn new(map: &impl Map, source: Point2D, target: Point2D) -> Frontier {
+        let mut frontier = Frontier {
+            target,
+            open_set: BinaryHeap::with_capacity(1024),
+            g_score: Grid::new(map.boundaries(), f64::INFINITY),
+            f_score: Grid::new(map.boundaries(), f64::INFINITY),
+            came_from: Grid::new(map.boundaries(), None),
+        };
+        let f_score = map.heuristic(&source, &target);
+        frontier.g_score.set(&source, 0.);
+        frontier.f_score.set(&source, f_score);
+        frontier.open_set.push(HeapElement { position: source, f_score });
+        frontier
+    }
+
+    /// Returns the smallest f score of the open set, dropping the outdated entries on the way.
+    fn top(&mut self) -> f64 {
+        while let Some(HeapElement { position, f_score }) = self.open_set.peek() {
+            if *f_score <= self.f_score.get(position) {
+                return *f_score;
+            }
+            self.open_set.pop();
+        }
+        f64::INFINITY
+    }
+
+    /// Expands the best cell of the open set, and updates `best` with the cost and the cell of the
+    /// shortest path found through the cells reached by both searches.
+    fn expand(&mut self, map: &impl Map, other: &Frontier, best: &mut (f64, Option<Point2D>)) {
+        let position = match self.open_set.pop() {
+            Some(HeapElement { position, .. }) => position,
+            None => return,
+        };
+        let g = self.g_score.get(&position);
+
+        for neighbor in map.neighbors(&position) {
+            if map.obstacle(&neighbor) { continue; }
+
+            let tentative_g_score = g + map.distance(&position, &neighbor);
+            if tentative_g_score < self.g_score.get(&neighbor) {
+                self.g_score.set(&neighbor, tentative_g_score);
+                self.came_from.set(&neighbor, Some(position));
+                let new_f_score = tentative_g_score + map.heuristic(&neighbor, &self.target);
+                self.f_score.set(&neighbor, new_f_score);
+                self.open_set.push(HeapElement { position: neighbor, f_score: new_f_score });
+
+                let cost = tentative_g_score + other.g_score.get(&neighbor);
+                if cost < best.0 {
+                    *best = (cost, Some(neighbor));
+                }
+            }
+        }
+    }
+
+    /// Returns the cells from `cell` back to the source of the search.
+    fn path_from(&self, cell: Point2D) -> Vec<Point2D> {
+        let mut path = vec![cell];
+        let mut pos = cell;
+        while let Some(previous) = self.came_from.get(&pos) {
+            path.push(previous);
+            pos = previous;
+        }
+        path
+    }
+}
+
+/// Searches a shortest path with two A* searches, one from each end, which meet in the middle.
+///
+/// Each search only explores about half of the distance, which pays off when the frontier of a
+/// single search grows large, e.g. along long corridors. The search with the smallest open set is
+/// expanded first, and both stop once the best path found through a cell reached by both
+/// searches is no longer than the smallest f score of either open set: any shorter path would go
+/// through a cell of this open set.
+///
+/// Paths go from cell to adjacent cell, as given by `Map::neighbors`.
+pub fn find_path_bidirectional(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    if map.obstacle(&start) || map.obstacle(&end) {
+        return Err(Box::new(NoPathFoundError()));
+    }
+    if start == end {
+        return Ok(vec![start]);
+    }
+
+    let mut forward = Frontier::new(map, start, end);
+    let mut backward = Frontier::new(map, end, start);
+    let mut best: (f64, Option<Point2D>) = (f64::INFINITY, None);
+
+    loop {
+        let (forward_top, backward_top) = (forward.top(), backward.top());
+        if forward_top >= best.0 || backward_top >= best.0 {
+            break;
+        }
+        if forward.open_set.len() <= backward.open_set.len() {
+            forward.expand(map, &backward, &mut best);
+        } else {
+            backward.expand(map, &forward, &mut best);
+        }
+    }
+
+    match best.1 {
+        Some(meeting) => {
+            let mut path = forward.path_from(meeting);
+            path.reverse();
+            path.extend(backward.path_from(meeting).into_iter().skip(1));
+            Ok(path)
+        }
+        None => Err(Box::new(NoPathFoundError())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::dstar_lite::DStarLite;
+
+    use super::*;
+
+    fn cost(map: &impl Map, path: &[Point2D]) -> f64 {
+        path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum()
+    }
+
+    #[test]
+    fn test_shortest_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#.......
+            ..#.####..
+            ..#.#.....
+            ..#.#G.#..
+            ....####..
+            ..........
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+
+        let path = find_path_bidirectional(&map, start, goal)?;
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| map.neighbors(&w[0]).any(|n| n == w[1]) && !map.obstacle(&w[1])));
+
+        let want = DStarLite::new(&map, start, goal).plan(&map)?;
+        assert!((cost(&map, &path) - cost(&map, &want)).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trivial_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ...
+            ...
+        ")?.map;
+        assert_eq!(find_path_bidirectional(&map, (1, 1), (1, 1))?, vec![(1, 1)]);
+        assert_eq!(find_path_bidirectional(&map, (0, 0), (1, 1))?, vec![(0, 0), (1, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ..#..
+            ..#..
+        ")?.map;
+        assert!(find_path_bidirectional(&map, (0, 0), (4, 1)).is_err());
+        assert!(find_path_bidirectional(&map, (0, 0), (2, 1)).is_err());
+        Ok(())
+    }
+}
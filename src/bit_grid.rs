@@ -0,0 +1,504 @@
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+
+use crate::errors::NoPathFoundError;
+use crate::grid::Grid;
+use crate::heap::HeapElement;
+use crate::line_of_sight::line_of_sight_with;
+use crate::map::{Bounded, Map};
+use crate::point::Point2D;
+
+const WORD_BITS: usize = 64;
+
+/// A set of bits stored in lines of 64 bits words.
+struct BitLines {
+    words: Vec<u64>,
+    words_per_line: usize,
+    lines: usize,
+    len: usize,
+}
+
+impl BitLines {
+    fn new(lines: usize, len: usize) -> BitLines {
+        let words_per_line = len.div_ceil(WORD_BITS);
+        BitLines {
+            words: vec![0; lines * words_per_line],
+            words_per_line,
+            lines,
+            len,
+        }
+    }
+
+    fn line(&self, line: usize) -> &[u64] {
+        &self.words[line * self.words_per_line..(line + 1) * self.words_per_line]
+    }
+
+    fn get(&self, line: usize, i: usize) -> bool {
+        self.line(line)[i / WORD_BITS] & 1 << (i % WORD_BITS) != 0
+    }
+
+    fn set(&mut self, line: usize, i: usize, value: bool) {
+        let word = &mut self.words[line * self.words_per_line + i / WORD_BITS];
+        if value {
+            *word |= 1 << (i % WORD_BITS);
+        } else {
+            *word &= !(1 << (i % WORD_BITS));
+        }
+    }
+
+    /// Returns the lowest set bit of the line in `from..=to`.
+    fn first_set(&self, line: usize, from: usize, to: usize) -> Option<usize> {
+        let words = self.line(line);
+        let mut i = from;
+        while i <= to {
+            let word = words[i / WORD_BITS] >> (i % WORD_BITS);
+            if word != 0 {
+                let found = i + word.trailing_zeros() as usize;
+                return if found <= to { Some(found) } else { None };
+            }
+            i = (i / WORD_BITS + 1) * WORD_BITS;
+        }
+        None
+    }
+
+    /// Returns the highest set bit of the line in `from..=to`.
+    fn last_set(&self, line: usize, from: usize, to: usize) -> Option<usize> {
+        let words = self.line(line);
+        let mut i = to as isize;
+        while i >= from as isize {
+            let shift = WORD_BITS - 1 - i as usize % WORD_BITS;
+            let word = words[i as usize / WORD_BITS] << shift;
+            if word != 0 {
+                let found = i - word.leading_zeros() as isize;
+                return if found >= from as isize { Some(found as usize) } else { None };
+            }
+            i = (i as usize / WORD_BITS * WORD_BITS) as isize - 1;
+        }
+        None
+    }
+
+    /// Returns the `k`-th word of the line, the bits out of the line being set.
+    fn word(&self, line: usize, k: isize) -> u64 {
+        if k < 0 || k as usize >= self.words_per_line {
+            return !0;
+        }
+        let word = self.line(line)[k as usize];
+        match self.len as isize - k * WORD_BITS as isize {
+            valid if valid >= WORD_BITS as isize => word,
+            valid => word | !0 << valid,
+        }
+    }
+
+    /// Returns the 64 bits of the line starting at `i` and going by `step` (1 or -1): bit `k` is
+    /// the bit `i + k * step`. Bits out of the lines are set.
+    fn window(&self, line: isize, i: isize, step: isize) -> u64 {
+        if line < 0 || line as usize >= self.lines {
+            return !0;
+        }
+        let start = if step > 0 { i } else { i - (WORD_BITS as isize - 1) };
+        let (k, shift) = (start.div_euclid(WORD_BITS as isize), start.rem_euclid(WORD_BITS as isize));
+        let mut bits = self.word(line as usize, k) >> shift;
+        if shift > 0 {
+            bits |= self.word(line as usize, k + 1) << (WORD_BITS as isize - shift);
+        }
+        if step > 0 { bits } else { bits.reverse_bits() }
+    }
+
+    /// Walks along `line` from `from` (excluded) by `step` (1 or -1) and returns the first jump
+    /// point: `goal`, or a cell with a forced neighbor on one of the adjacent lines, i.e. a cell
+    /// beside an obstacle followed by a free cell. Returns `None` when the walk meets an obstacle
+    /// or the end of the line first.
+    ///
+    /// Each iteration checks 64 cells with a few word operations.
+    fn jump(&self, line: isize, from: isize, step: isize, goal: Option<isize>) -> Option<isize> {
+        let mut i = from + step;
+        loop {
+            let blocked = self.window(line, i, step).trailing_zeros();
+            let forced = [line - 1, line + 1].iter()
+                .map(|side| self.window(*side, i, step) & !self.window(*side, i + step, step))
+                .fold(0, |forced, bits| forced | bits)
+                .trailing_zeros();
+            let to_goal = goal.map(|goal| (goal - i) * step)
+                .filter(|offset| (0..WORD_BITS as isize).contains(offset))
+                .map_or(WORD_BITS as u32, |offset| offset as u32);
+
+            let first = forced.min(to_goal);
+            if first < blocked {
+                return Some(i + first as isize * step);
+            }
+            if blocked < WORD_BITS as u32 {
+                return None;
+            }
+            i += WORD_BITS as isize * step;
+        }
+    }
+}
+
+/// An obstacle grid storing one bit per cell instead of one byte.
+///
+/// Obstacles are stored twice, once row by row and once column by column, so runs of cells along
+/// both axes can be scanned 64 cells at a time.
+pub struct BitGrid {
+    rows: BitLines,
+    columns: BitLines,
+    min: Point2D,
+    max: Point2D,
+}
+
+impl BitGrid {
+    pub fn new((min, max): (Point2D, Point2D)) -> BitGrid {
+        let (width, height) = (
+            usize::try_from(max.0 - min.0).unwrap(),
+            usize::try_from(max.1 - min.1).unwrap(),
+        );
+        BitGrid {
+            rows: BitLines::new(height, width),
+            columns: BitLines::new(width, height),
+            min,
+            max,
+        }
+    }
+
+    pub fn get(&self, point: &Point2D) -> bool {
+        let (x, y) = self.to_index(point);
+        self.rows.get(y, x)
+    }
+
+    pub fn set(&mut self, point: &Point2D, obstacle: bool) {
+        let (x, y) = self.to_index(point);
+        self.rows.set(y, x, obstacle);
+        self.columns.set(x, y, obstacle);
+    }
+
+    /// Returns the first obstacle met when walking along row `y` from `from_x` to `to_x` (both
+    /// included), in either direction.
+    pub fn first_obstacle_in_row(&self, y: isize, from_x: isize, to_x: isize) -> Option<isize> {
+        let (from, row) = self.to_index(&(from_x, y));
+        let (to, _) = self.to_index(&(to_x, y));
+        let found = if from <= to {
+            self.rows.first_set(row, from, to)
+        } else {
+            self.rows.last_set(row, to, from)
+        };
+        found.map(|x| x as isize + self.min.0)
+    }
+
+    /// Returns the first obstacle met when walking along column `x` from `from_y` to `to_y` (both
+    /// included), in either direction.
+    pub fn first_obstacle_in_column(&self, x: isize, from_y: isize, to_y: isize) -> Option<isize> {
+        let (column, from) = self.to_index(&(x, from_y));
+        let (_, to) = self.to_index(&(x, to_y));
+        let found = if from <= to {
+            self.columns.first_set(column, from, to)
+        } else {
+            self.columns.last_set(column, to, from)
+        };
+        found.map(|y| y as isize + self.min.1)
+    }
+
+    fn to_index(&self, (x, y): &Point2D) -> (usize, usize) {
+        let (min_x, min_y) = self.min;
+        let (max_x, max_y) = self.max;
+        assert!(*x >= min_x && *x < max_x && *y >= min_y && *y < max_y, "{:?} is out of bounds", (x, y));
+        ((x - min_x) as usize, (y - min_y) as usize)
+    }
+}
+
+impl From<&Grid<bool>> for BitGrid {
+    fn from(obstacles: &Grid<bool>) -> BitGrid {
+        let mut grid = BitGrid::new(obstacles.boundaries());
+        let ((min_x, min_y), (max_x, max_y)) = obstacles.boundaries();
+        for x in min_x..max_x {
+            for y in min_y..max_y {
+                if obstacles.get(&(x, y)) {
+                    grid.set(&(x, y), true);
+                }
+            }
+        }
+        grid
+    }
+}
+
+impl Map for BitGrid {
+    fn obstacle(&self, point: &Point2D) -> bool {
+        self.get(point)
+    }
+
+    /// Horizontal and vertical lines are checked a word at a time, other lines cell by cell.
+    fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+        let (start_x, start_y) = *start;
+        let (end_x, end_y) = *end;
+        if start_y == end_y {
+            return self.first_obstacle_in_row(start_y, start_x, end_x).is_none();
+        }
+        if start_x == end_x {
+            return self.first_obstacle_in_column(start_x, start_y, end_y).is_none();
+        }
+        line_of_sight_with(start, end, |pos| self.get(pos))
+    }
+}
+
+impl Bounded for BitGrid {
+    fn boundaries(&self) -> (Point2D, Point2D) {
+        (self.min, self.max)
+    }
+}
+
+impl BitGrid {
+    /// Returns whether `(x, y)`, in indices from the lower corner, is an obstacle or out of the grid.
+    fn blocked(&self, x: isize, y: isize) -> bool {
+        x < 0 || y < 0 || x as usize >= self.columns.lines || y as usize >= self.rows.lines || self.rows.get(y as usize, x as usize)
+    }
+
+    /// Returns the first jump point from `(x, y)` in direction `(dx, dy)`, in indices from the lower
+    /// corner. Horizontal and vertical jumps scan words, diagonal jumps go cell by cell and stop
+    /// where a horizontal or vertical jump finds a jump point.
+    fn jump(&self, (x, y): (isize, isize), (dx, dy): (isize, isize), goal: (isize, isize)) -> Option<(isize, isize)> {
+        if dy == 0 {
+            let goal = if goal.1 == y { Some(goal.0) } else { None };
+            return self.rows.jump(y, x, dx, goal).map(|x| (x, y));
+        }
+        if dx == 0 {
+            let goal = if goal.0 == x { Some(goal.1) } else { None };
+            return self.columns.jump(x, y, dy, goal).map(|y| (x, y));
+        }
+
+        let (mut x, mut y) = (x, y);
+        loop {
+            x += dx;
+            y += dy;
+            if self.blocked(x, y) {
+                return None;
+            }
+            let forced = (self.blocked(x - dx, y) && !self.blocked(x - dx, y + dy))
+                || (self.blocked(x, y - dy) && !self.blocked(x + dx, y - dy));
+            if (x, y) == goal || forced || self.jump((x, y), (dx, 0), goal).is_some() || self.jump((x, y), (0, dy), goal).is_some() {
+                return Some((x, y));
+            }
+        }
+    }
+
+    /// Returns the directions to search from `(x, y)` reached in direction `(dx, dy)`: the natural
+    /// neighbors, which no shorter path reaches without `(x, y)`, and the forced ones.
+    fn directions(&self, (x, y): (isize, isize), (dx, dy): (isize, isize)) -> Vec<(isize, isize)> {
+        match (dx, dy) {
+            (0, 0) => vec![(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1)],
+            (dx, 0) => {
+                let mut directions = vec![(dx, 0)];
+                directions.extend([1, -1].iter().filter(|side| self.blocked(x, y + *side)).map(|side| (dx, *side)));
+                directions
+            }
+            (0, dy) => {
+                let mut directions = vec![(0, dy)];
+                directions.extend([1, -1].iter().filter(|side| self.blocked(x + *side, y)).map(|side| (*side, dy)));
+                directions
+            }
+            (dx, dy) => {
+                let mut directions = vec![(dx, 0), (0, dy), (dx, dy)];
+                if self.blocked(x - dx, y) {
+                    directions.push((-dx, dy));
+                }
+                if self.blocked(x, y - dy) {
+                    directions.push((dx, -dy));
+                }
+                directions
+            }
+        }
+    }
+}
+
+/// Searches a shortest path from cell to adjacent cell, as given by `Map::neighbors`, with a jump
+/// point search (JPS): only the cells where a shortest path may turn are expanded.
+///
+/// Horizontal and vertical jumps scan the bits of the grid 64 cells at a time, which makes large
+/// open areas cheap to cross. The path is made of the jump points, between which it goes
+/// horizontally, vertically or diagonally. As with `Map::neighbors`, diagonal moves may pass
+/// between two obstacles touching by a corner.
+pub fn find_path_jps(grid: &BitGrid, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    if grid.obstacle(&start) || grid.obstacle(&end) {
+        return Err(Box::new(NoPathFoundError()));
+    }
+    let (min_x, min_y) = grid.min;
+    let to_index = |(x, y): Point2D| (x - min_x, y - min_y);
+    let to_point = |(x, y): (isize, isize)| (x + min_x, y + min_y);
+    let goal = to_index(end);
+
+    let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::with_capacity(1024);
+    let mut g_score: Grid<f64> = Grid::new(grid.boundaries(), f64::INFINITY);
+    let mut came_from: Grid<Option<Point2D>> = Grid::new(grid.boundaries(), None);
+    g_score.set(&start, 0.);
+    open_set.push(HeapElement { position: start, f_score: grid.heuristic(&start, &end) });
+
+    while let Some(HeapElement { position, f_score }) = open_set.pop() {
+        let g = g_score.get(&position);
+        if f_score > g + grid.heuristic(&position, &end) { continue; }
+        if position == end {
+            let mut path = vec![end];
+            let mut pos = end;
+            while let Some(previous) = came_from.get(&pos) {
+                path.push(previous);
+                pos = previous;
+            }
+            path.reverse();
+            return Ok(path);
+        }
+
+        let direction = match came_from.get(&position) {
+            Some((px, py)) => ((position.0 - px).signum(), (position.1 - py).signum()),
+            None => (0, 0),
+        };
+        for direction in grid.directions(to_index(position), direction) {
+            let neighbor = match grid.jump(to_index(position), direction, goal) {
+                Some(jump_point) => to_point(jump_point),
+                None => continue,
+            };
+            let tentative_g_score = g + grid.distance(&position, &neighbor);
+            if tentative_g_score < g_score.get(&neighbor) {
+                g_score.set(&neighbor, tentative_g_score);
+                came_from.set(&neighbor, Some(position));
+                let new_f_score = tentative_g_score + grid.heuristic(&neighbor, &end);
+                open_set.push(HeapElement { position: neighbor, f_score: new_f_score });
+            }
+        }
+    }
+    Err(Box::new(NoPathFoundError()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dstar_lite::DStarLite;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    /// Deterministic pseudo-random obstacles.
+    fn random_obstacles(boundaries: (Point2D, Point2D), density: u64) -> Grid<bool> {
+        let mut grid = Grid::new(boundaries, false);
+        let ((min_x, min_y), (max_x, max_y)) = boundaries;
+        let mut state = 42u64;
+        for x in min_x..max_x {
+            for y in min_y..max_y {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                grid.set(&(x, y), (state >> 33) % 100 < density);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut grid = BitGrid::new(((-70, -3), (70, 3)));
+
+        grid.set(&(-70, -3), true);
+        grid.set(&(0, 0), true);
+        grid.set(&(69, 2), true);
+        grid.set(&(0, 0), false);
+
+        assert!(grid.get(&(-70, -3)));
+        assert!(!grid.get(&(0, 0)));
+        assert!(grid.get(&(69, 2)));
+        assert!(!grid.get(&(68, 2)));
+    }
+
+    #[test]
+    fn test_first_obstacle() {
+        let mut grid = BitGrid::new(((-100, -100), (100, 100)));
+        grid.set(&(-90, 5), true);
+        grid.set(&(70, 5), true);
+        grid.set(&(5, -80), true);
+
+        assert_eq!(grid.first_obstacle_in_row(5, 0, 99), Some(70));
+        assert_eq!(grid.first_obstacle_in_row(5, 0, -100), Some(-90));
+        assert_eq!(grid.first_obstacle_in_row(5, -89, 69), None);
+        assert_eq!(grid.first_obstacle_in_row(5, 70, 70), Some(70));
+        assert_eq!(grid.first_obstacle_in_row(6, -100, 99), None);
+        assert_eq!(grid.first_obstacle_in_column(5, 99, -100), Some(-80));
+        assert_eq!(grid.first_obstacle_in_column(5, -100, 99), Some(-80));
+        assert_eq!(grid.first_obstacle_in_column(5, -79, 99), None);
+    }
+
+    #[test]
+    fn test_same_line_of_sight_as_grid_map() {
+        let boundaries = ((-3, -5), (150, 130));
+        let obstacles = random_obstacles(boundaries, 2);
+        let bit_grid = BitGrid::from(&obstacles);
+        let grid_map = GridMap::new(obstacles);
+
+        let points = [(-3, -5), (149, -5), (-3, 129), (149, 129), (10, 10), (10, 120), (140, 10), (77, 64)];
+        for a in points.iter() {
+            for b in points.iter() {
+                assert_eq!(bit_grid.line_of_sight(a, b), grid_map.line_of_sight(a, b), "{:?} -> {:?}", a, b);
+            }
+        }
+        for x in -3..150 {
+            for y in -5..130 {
+                assert_eq!(bit_grid.obstacle(&(x, y)), grid_map.obstacle(&(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_window() {
+        let mut grid = BitGrid::new(((0, 0), (70, 1)));
+        grid.set(&(3, 0), true);
+        grid.set(&(66, 0), true);
+
+        assert_eq!(grid.rows.window(0, 0, 1), 1 << 3);
+        assert_eq!(grid.rows.window(0, 3, 1), 1 | 1 << 63);
+        assert_eq!(grid.rows.window(0, 10, 1), 1 << 56 | !0 << 60);
+        assert_eq!(grid.rows.window(0, 66, -1), 1 | 1 << 63);
+        assert_eq!(grid.rows.window(0, 2, -1), !0 << 3);
+        assert_eq!(grid.rows.window(1, 0, 1), !0);
+    }
+
+    #[test]
+    fn test_jps_same_cost_as_adjacent_search() -> Result<(), Box<dyn std::error::Error>> {
+        let boundaries = ((-20, -10), (130, 90));
+        let obstacles = random_obstacles(boundaries, 20);
+        let bit_grid = BitGrid::from(&obstacles);
+        let grid_map = GridMap::new(obstacles);
+        let cost = |path: &[Point2D]| path.windows(2).map(|w| grid_map.distance(&w[0], &w[1])).sum::<f64>();
+
+        let cells = [(-20, -10), (129, 89), (0, 0), (100, 20), (-5, 80), (64, 45)];
+        let mut found = 0;
+        for start in cells.iter().filter(|cell| !bit_grid.obstacle(cell)) {
+            for goal in cells.iter().filter(|cell| !bit_grid.obstacle(cell)) {
+                let want = DStarLite::new(&grid_map, *start, *goal).plan(&grid_map);
+                let got = find_path_jps(&bit_grid, *start, *goal);
+                assert_eq!(got.is_ok(), want.is_ok(), "{:?} -> {:?}", start, goal);
+                let (got, want) = match (got, want) {
+                    (Ok(got), Ok(want)) => (got, want),
+                    _ => continue,
+                };
+                found += 1;
+
+                assert_eq!(got.first(), Some(start));
+                assert_eq!(got.last(), Some(goal));
+                assert!((cost(&got) - cost(&want)).abs() < 1e-6, "{:?} -> {:?}", start, goal);
+                for w in got.windows(2) {
+                    let (dx, dy) = (w[1].0 - w[0].0, w[1].1 - w[0].1);
+                    assert!(dx == 0 || dy == 0 || dx.abs() == dy.abs());
+                    let steps = dx.abs().max(dy.abs());
+                    assert!((0..=steps).all(|k| !bit_grid.obstacle(&(w[0].0 + k * dx.signum(), w[0].1 + k * dy.signum()))));
+                }
+            }
+        }
+        assert!(found >= 16);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jps_long_runs() -> Result<(), Box<dyn std::error::Error>> {
+        let mut grid = BitGrid::new(((0, 0), (300, 3)));
+        for x in 0..299 {
+            grid.set(&(x, 1), true);
+        }
+        assert_eq!(find_path_jps(&grid, (0, 0), (0, 2))?, vec![(0, 0), (298, 0), (299, 1), (298, 2), (0, 2)]);
+        assert_eq!(find_path_jps(&grid, (5, 0), (250, 0))?, vec![(5, 0), (250, 0)]);
+
+        grid.set(&(299, 1), true);
+        assert!(find_path_jps(&grid, (0, 0), (0, 2)).is_err());
+        assert!(find_path_jps(&grid, (0, 1), (0, 2)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ndarray::Array2;
+
+use crate::grid::CellStorage;
+use crate::line_of_sight::line_of_sight_with;
+use crate::map::{Bounded, Map};
+use crate::point::Point2D;
+
+/// Boundaries of a `ChunkedMap` created without explicit boundaries, large enough to be
+/// considered unbounded while keeping distances exact as `f64`.
+const UNBOUNDED: (Point2D, Point2D) = ((-(1 << 40), -(1 << 40)), (1 << 40, 1 << 40));
+
+/// Returns the chunk containing `point` and the index of `point` inside this chunk.
+fn split((x, y): &Point2D, chunk_size: usize) -> (Point2D, (usize, usize)) {
+    let size = chunk_size as isize;
+    (
+        (x.div_euclid(size), y.div_euclid(size)),
+        (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize),
+    )
+}
+
+/// A sparse, unbounded grid made of square chunks allocated on the first write.
+///
+/// Cells of chunks that were never written have the default value.
+pub struct ChunkedGrid<T> {
+    chunks: HashMap<Point2D, Array2<T>>,
+    chunk_size: usize,
+    default: T,
+}
+
+impl<T: Copy> ChunkedGrid<T> {
+    pub fn new(chunk_size: usize, default: T) -> ChunkedGrid<T> {
+        assert!(chunk_size > 0, "chunks cannot be empty");
+        ChunkedGrid {
+            chunks: HashMap::new(),
+            chunk_size,
+            default,
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns the number of chunks allocated so far.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn get(&self, point: &Point2D) -> T {
+        let (chunk, index) = split(point, self.chunk_size);
+        match self.chunks.get(&chunk) {
+            Some(cells) => cells[index],
+            None => self.default,
+        }
+    }
+
+    pub fn set(&mut self, point: &Point2D, elem: T) {
+        let (chunk, index) = split(point, self.chunk_size);
+        let (size, default) = (self.chunk_size, self.default);
+        let cells = self.chunks
+            .entry(chunk)
+            .or_insert_with(|| Array2::from_elem((size, size), default));
+        cells[index] = elem;
+    }
+}
+
+impl<T: Copy> CellStorage<T> for ChunkedGrid<T> {
+    fn get(&self, point: &Point2D) -> T {
+        ChunkedGrid::get(self, point)
+    }
+
+    fn set(&mut self, point: &Point2D, elem: T) {
+        ChunkedGrid::set(self, point, elem)
+    }
+}
+
+/// Generates the obstacles of the chunk at the given chunk coordinates, as an array of
+/// `chunk_size x chunk_size` cells indexed by `[x, y]`.
+pub type ChunkLoader = Box<dyn Fn(Point2D) -> Array2<bool>>;
+
+/// A map of a huge or unbounded world whose obstacles are split in square chunks, loaded through a
+/// user callback the first time one of their cells is accessed.
+///
+/// Chunk `(i, j)` contains the cells `(i * chunk_size + x, j * chunk_size + y)` for `x` and `y` in
+/// `0..chunk_size`. Searches over such maps should use `find_path_chunked`, which does not
+/// allocate scores for the whole map.
+pub struct ChunkedMap {
+    chunks: RefCell<HashMap<Point2D, Array2<bool>>>,
+    chunk_size: usize,
+    loader: ChunkLoader,
+    boundaries: (Point2D, Point2D),
+}
+
+impl ChunkedMap {
+    pub fn new(chunk_size: usize, loader: ChunkLoader) -> ChunkedMap {
+        assert!(chunk_size > 0, "chunks cannot be empty");
+        ChunkedMap {
+            chunks: RefCell::new(HashMap::new()),
+            chunk_size,
+            loader,
+            boundaries: UNBOUNDED,
+        }
+    }
+
+    /// Restricts the map to the given boundaries, searches do not go past them.
+    pub fn with_boundaries(self, boundaries: (Point2D, Point2D)) -> ChunkedMap {
+        ChunkedMap { boundaries, ..self }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns the number of chunks currently loaded.
+    pub fn loaded_chunks(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    /// Drops a loaded chunk, e.g. when the world changed or to free memory. It is loaded again the
+    /// next time one of its cells is accessed.
+    pub fn unload(&self, chunk: &Point2D) {
+        self.chunks.borrow_mut().remove(chunk);
+    }
+
+    fn load(&self, chunk: Point2D) -> Array2<bool> {
+        let cells = (self.loader)(chunk);
+        assert_eq!(
+            cells.dim(), (self.chunk_size, self.chunk_size),
+            "chunk {:?} does not have the expected size", chunk,
+        );
+        cells
+    }
+}
+
+impl Map for ChunkedMap {
+    fn obstacle(&self, point: &Point2D) -> bool {
+        let (chunk, index) = split(point, self.chunk_size);
+        if let Some(cells) = self.chunks.borrow().get(&chunk) {
+            return cells[index];
+        }
+        let cells = self.load(chunk);
+        let obstacle = cells[index];
+        self.chunks.borrow_mut().insert(chunk, cells);
+        obstacle
+    }
+
+    fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+        line_of_sight_with(start, end, |pos| self.obstacle(pos))
+    }
+}
+
+impl Bounded for ChunkedMap {
+    fn boundaries(&self) -> (Point2D, Point2D) {
+        self.boundaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::find_path::{find_path_chunked, find_path_impl};
+    use crate::grid::Grid;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    /// Vertical walls every 16 cells with a gap every 16 cells, shifted from one wall to the next.
+    fn walls((x, y): Point2D) -> bool {
+        x.rem_euclid(16) == 0 && (y - x).rem_euclid(16) != 0
+    }
+
+    fn walls_loader(chunk_size: usize) -> ChunkLoader {
+        Box::new(move |(i, j)| {
+            let size = chunk_size as isize;
+            Array2::from_shape_fn((chunk_size, chunk_size), |(x, y)| {
+                walls((i * size + x as isize, j * size + y as isize))
+            })
+        })
+    }
+
+    #[test]
+    fn test_chunked_grid() {
+        let mut grid = ChunkedGrid::new(8, 0);
+        grid.set(&(-1, -1), 1);
+        grid.set(&(7, 0), 2);
+        grid.set(&(8, 0), 3);
+
+        assert_eq!(grid.get(&(-1, -1)), 1);
+        assert_eq!(grid.get(&(7, 0)), 2);
+        assert_eq!(grid.get(&(8, 0)), 3);
+        assert_eq!(grid.get(&(1000, -1000)), 0);
+        assert_eq!(grid.chunk_count(), 3);
+    }
+
+    #[test]
+    fn test_lazy_loading() {
+        let map = ChunkedMap::new(32, walls_loader(32));
+
+        assert!(map.obstacle(&(-16, -15)));
+        assert!(!map.obstacle(&(-16, -16)));
+        assert!(!map.obstacle(&(1_000_001, 3)));
+        assert_eq!(map.loaded_chunks(), 2);
+
+        map.unload(&(-1, -1));
+        assert_eq!(map.loaded_chunks(), 1);
+    }
+
+    #[test]
+    fn test_same_path_as_grid_map() -> Result<(), Box<dyn std::error::Error>> {
+        let boundaries = ((-40, -40), (60, 60));
+        let map = ChunkedMap::new(32, walls_loader(32)).with_boundaries(boundaries);
+        let mut obstacles = Grid::new(boundaries, false);
+        for x in -40..60 {
+            for y in -40..60 {
+                obstacles.set(&(x, y), walls((x, y)));
+            }
+        }
+        let grid_map = GridMap::new(obstacles);
+
+        let (start, end) = ((-35, 20), (55, -30));
+        let got = find_path_chunked(&map, start, end)?;
+        assert_eq!(got, find_path_impl(&grid_map, start, end)?);
+        assert_eq!(got, find_path_impl(&map, start, end)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbounded_search() -> Result<(), Box<dyn std::error::Error>> {
+        let map = ChunkedMap::new(64, walls_loader(64));
+
+        let got = find_path_chunked(&map, (1_000_001, -500), (1_000_200, -400))?;
+        assert_eq!(got.first(), Some(&(1_000_001, -500)));
+        assert_eq!(got.last(), Some(&(1_000_200, -400)));
+        assert!(got.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(map.loaded_chunks() < 50);
+        Ok(())
+    }
+}
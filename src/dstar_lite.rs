@@ -0,0 +1,223 @@
+use std::cmp::Ordering;
+
+use crate::grid::Grid;
+use crate::heap::DecreaseKeyHeap;
+use crate::map::{descend, Map, step_cost};
+use crate::point::Point2D;
+
+/// Priority of a cell in the open set, compared lexicographically.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Key(f64, f64);
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.total_cmp(&other.1))
+    }
+}
+
+/// A D* Lite planner, which repairs its path when the robot moves or discovers changed cells
+/// instead of searching again from scratch.
+///
+/// The search goes backward from the goal, so the distances to the goal stay valid when the robot
+/// moves. Paths go from cell to adjacent cell, as given by `Map::neighbors`, and are only repaired
+/// around the cells reported as changed.
+pub struct DStarLite {
+    start: Point2D,
+    goal: Point2D,
+    /// Start when the heuristic of the keys of the open set was last computed.
+    last: Point2D,
+    /// Accumulated heuristic offset applied to new keys since the robot started moving.
+    k_m: f64,
+    g: Grid<f64>,
+    rhs: Grid<f64>,
+    open_set: DecreaseKeyHeap<Point2D, Key>,
+}
+
+impl DStarLite {
+    pub fn new(map: &impl Map, start: Point2D, goal: Point2D) -> DStarLite {
+        let boundaries = map.boundaries();
+        let mut planner = DStarLite {
+            start,
+            goal,
+            last: start,
+            k_m: 0.,
+            g: Grid::new(boundaries, f64::INFINITY),
+            rhs: Grid::new(boundaries, f64::INFINITY),
+            open_set: DecreaseKeyHeap::new(),
+        };
+        planner.rhs.set(&goal, 0.);
+        let key = planner.key(map, &goal);
+        planner.open_set.push(goal, key);
+        planner
+    }
+
+    pub fn start(&self) -> Point2D {
+        self.start
+    }
+
+    pub fn goal(&self) -> Point2D {
+        self.goal
+    }
+
+    /// Returns the cost of the shortest path from `cell` to the goal, as of the last planning.
+    pub fn cost_to_goal(&self, cell: &Point2D) -> f64 {
+        self.g.get(cell)
+    }
+
+    /// Computes the shortest path from the current start to the goal, reusing the previous
+    /// computations.
+    pub fn plan(&mut self, map: &impl Map) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        self.compute_shortest_path(map);
+        descend(map, &self.g, self.start, self.goal)
+    }
+
+    /// Moves the robot to `position`, takes into account the cells whose obstacle status changed
+    /// in `map` since the last call, and returns the repaired path.
+    pub fn update(&mut self, map: &impl Map, position: Point2D, changed: &[Point2D]) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        self.start = position;
+        self.k_m += map.heuristic(&self.last, &self.start);
+        self.last = self.start;
+        for cell in changed.iter() {
+            self.update_vertex(map, cell);
+            for neighbor in map.neighbors(cell) {
+                self.update_vertex(map, &neighbor);
+            }
+        }
+        self.plan(map)
+    }
+
+    fn key(&self, map: &impl Map, cell: &Point2D) -> Key {
+        let min = self.g.get(cell).min(self.rhs.get(cell));
+        Key(min + map.heuristic(&self.start, cell) + self.k_m, min)
+    }
+
+    fn update_vertex(&mut self, map: &impl Map, cell: &Point2D) {
+        if *cell != self.goal {
+            let rhs = map.neighbors(cell)
+                .map(|n| step_cost(map, cell, &n) + self.g.get(&n))
+                .fold(f64::INFINITY, f64::min);
+            self.rhs.set(cell, rhs);
+        }
+        if self.g.get(cell) != self.rhs.get(cell) {
+            let key = self.key(map, cell);
+            self.open_set.push(*cell, key);
+        } else {
+            self.open_set.remove(cell);
+        }
+    }
+
+    fn compute_shortest_path(&mut self, map: &impl Map) {
+        while let Some(top) = self.open_set.peek() {
+            let start = self.start;
+            if top.f_score >= self.key(map, &start) && self.rhs.get(&start) == self.g.get(&start) {
+                break;
+            }
+
+            let (old_key, cell) = (top.f_score, top.position);
+            let new_key = self.key(map, &cell);
+            if old_key < new_key {
+                self.open_set.push(cell, new_key);
+            } else if self.g.get(&cell) > self.rhs.get(&cell) {
+                self.g.set(&cell, self.rhs.get(&cell));
+                self.open_set.remove(&cell);
+                for neighbor in map.neighbors(&cell) {
+                    self.update_vertex(map, &neighbor);
+                }
+            } else {
+                self.g.set(&cell, f64::INFINITY);
+                self.update_vertex(map, &cell);
+                for neighbor in map.neighbors(&cell) {
+                    self.update_vertex(map, &neighbor);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::grid::Grid;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    fn cost(map: &impl Map, path: &[Point2D]) -> f64 {
+        path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum()
+    }
+
+    fn set_obstacle(map: &GridMap, cell: Point2D) -> GridMap {
+        let mut obstacles: Grid<bool> = map.obstacles().clone();
+        obstacles.set(&cell, true);
+        GridMap::new(obstacles)
+    }
+
+    #[test]
+    fn test_plan() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#...
+            ..#.#.
+            ....#G
+        ")?;
+        let mut planner = DStarLite::new(&map, start.unwrap(), goal.unwrap());
+
+        let path = planner.plan(&map)?;
+        assert_eq!(path.first(), start.as_ref());
+        assert_eq!(path.last(), goal.as_ref());
+        assert!(path.windows(2).all(|w| map.neighbors(&w[0]).any(|n| n == w[1]) && !map.obstacle(&w[1])));
+        assert!((cost(&map, &path) - (1. + 5. * 2f64.sqrt())).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replan_after_changes() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.........
+            ..........
+            ....##....
+            ....##....
+            .........G
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let mut planner = DStarLite::new(&map, start, goal);
+        let path = planner.plan(&map)?;
+
+        // The robot moves two steps then discovers an obstacle on its path.
+        let position = path[2];
+        let blocked = path[5];
+        let map = set_obstacle(&map, blocked);
+        let path = planner.update(&map, position, &[blocked])?;
+
+        assert_eq!(path.first(), Some(&position));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(!path.contains(&blocked));
+        let fresh = DStarLite::new(&map, position, goal).plan(&map)?;
+        assert!((cost(&map, &path) - cost(&map, &fresh)).abs() < 1e-9);
+        assert!((planner.cost_to_goal(&position) - cost(&map, &path)).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#..
+            ..#.G
+        ")?;
+        let mut planner = DStarLite::new(&map, start.unwrap(), goal.unwrap());
+        assert!(planner.plan(&map).is_err());
+
+        let mut obstacles: Grid<bool> = map.obstacles().clone();
+        obstacles.set(&(2, 0), false);
+        let map = GridMap::new(obstacles);
+        assert!(planner.update(&map, start.unwrap(), &[(2, 0)]).is_ok());
+        Ok(())
+    }
+}
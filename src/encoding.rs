@@ -0,0 +1,318 @@
+use std::convert::TryFrom;
+use std::convert::TryInto;
+
+use ndarray::Array2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MapFormatError;
+use crate::frame::Frame;
+use crate::grid::Grid;
+use crate::map::{Bounded, GridMap};
+
+const MAGIC: &[u8; 4] = b"GPM\x01";
+const WRAPPING: u8 = 1;
+const BITS: u8 = 0;
+const RUNS: u8 = 1;
+/// Largest number of cells a decoded map may hold, since runs can describe any size in a few bytes.
+const MAX_CELLS: usize = 1 << 30;
+
+fn format_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("binary map: {}", msg)))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads bytes sequentially, failing instead of panicking on truncated input.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+        if self.bytes.len() < len {
+            return Err(format_error("truncated data"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn std::error::Error>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i64(&mut self) -> Result<i64, Box<dyn std::error::Error>> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, Box<dyn std::error::Error>> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn varint(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut value = 0usize;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(format_error("invalid varint"))
+    }
+}
+
+/// Encodes cells as a bit set, 8 cells per byte.
+fn encode_bits(cells: &[bool]) -> Vec<u8> {
+    cells.chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, cell)| byte | (*cell as u8) << i))
+        .collect()
+}
+
+/// Encodes cells as the lengths of the runs of identical cells, the first run being free cells.
+fn encode_runs(cells: &[bool]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut current = false;
+    let mut len = 0;
+    for cell in cells.iter() {
+        if *cell != current {
+            write_varint(&mut bytes, len);
+            current = *cell;
+            len = 0;
+        }
+        len += 1;
+    }
+    write_varint(&mut bytes, len);
+    bytes
+}
+
+impl GridMap {
+    /// Encodes the map in a compact binary format.
+    ///
+    /// The header holds the boundaries, the frame and whether the map wraps. Obstacles are either
+    /// bit-packed or run-length encoded, whichever is the smallest, so large maps made of big free
+    /// areas and walls only take a few bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries();
+        let frame = self.frame();
+        let (origin_x, origin_y) = frame.origin();
+
+        let mut bytes = MAGIC.to_vec();
+        for v in [min_x, min_y, max_x, max_y].iter() {
+            bytes.extend_from_slice(&(*v as i64).to_le_bytes());
+        }
+        for v in [origin_x, origin_y, frame.resolution(), frame.rotation()].iter() {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.push(if self.wrapping() { WRAPPING } else { 0 });
+
+        let cells: Vec<bool> = self.obstacles().as_array().iter().cloned().collect();
+        let bits = encode_bits(&cells);
+        let runs = encode_runs(&cells);
+        if runs.len() < bits.len() {
+            bytes.push(RUNS);
+            bytes.extend(runs);
+        } else {
+            bytes.push(BITS);
+            bytes.extend(bits);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<GridMap, Box<dyn std::error::Error>> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(format_error("invalid header"));
+        }
+
+        let mut bound = || -> Result<isize, Box<dyn std::error::Error>> { Ok(isize::try_from(reader.i64()?)?) };
+        let min = (bound()?, bound()?);
+        let max = (bound()?, bound()?);
+        let shape = match (max.0.checked_sub(min.0), max.1.checked_sub(min.1)) {
+            (Some(width), Some(height)) if width >= 0 && height >= 0 => (width as usize, height as usize),
+            _ => return Err(format_error("invalid boundaries")),
+        };
+        let len = shape.0.checked_mul(shape.1).ok_or_else(|| format_error("invalid boundaries"))?;
+        if len > MAX_CELLS {
+            return Err(format_error("map too large"));
+        }
+
+        let origin = (reader.f64()?, reader.f64()?);
+        let frame = Frame::new(origin, reader.f64()?).with_rotation(reader.f64()?);
+        let flags = reader.u8()?;
+
+        // The size of the map comes from the header, so cells are only allocated once the data is
+        // known to hold them: a few bytes of runs may still describe a large map.
+        let cells = match reader.u8()? {
+            BITS => {
+                let bits = reader.take(len.div_ceil(8))?;
+                (0..len).map(|i| bits[i / 8] & 1 << (i % 8) != 0).collect()
+            }
+            RUNS => {
+                let mut cells = Vec::new();
+                let mut current = false;
+                while cells.len() < len {
+                    let run = reader.varint()?;
+                    if run > len - cells.len() {
+                        return Err(format_error("runs longer than the map"));
+                    }
+                    cells.extend(std::iter::repeat_n(current, run));
+                    current = !current;
+                }
+                cells
+            }
+            _ => return Err(format_error("unknown encoding")),
+        };
+
+        let obstacles = Grid::with_offset(Array2::from_shape_vec(shape, cells)?, min);
+        Ok(GridMap::with_frame(obstacles, frame).with_wrapping(flags & WRAPPING != 0))
+    }
+}
+
+/// Serialized form of a `GridMap`, using the encoding of `GridMap::to_bytes`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CompactGridMap(Vec<u8>);
+
+#[cfg(feature = "serde")]
+impl From<GridMap> for CompactGridMap {
+    fn from(map: GridMap) -> CompactGridMap {
+        CompactGridMap(map.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CompactGridMap> for GridMap {
+    type Error = String;
+
+    fn try_from(CompactGridMap(bytes): CompactGridMap) -> Result<GridMap, String> {
+        GridMap::from_bytes(&bytes).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Map;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip_runs() -> Result<(), Box<dyn std::error::Error>> {
+        let mut obstacles = Grid::new(((-500, -300), (500, 700)), false);
+        for y in -300..700 {
+            obstacles.set(&(42, y), true);
+        }
+        let map = GridMap::with_frame(obstacles, Frame::new((1.5, -2.), 0.05).with_rotation(0.3));
+
+        let bytes = map.to_bytes();
+        assert!(bytes.len() < 100);
+
+        let got = GridMap::from_bytes(&bytes)?;
+        assert_eq!(got.boundaries(), map.boundaries());
+        assert_eq!(got.frame(), map.frame());
+        assert_eq!(got.obstacles().as_array(), map.obstacles().as_array());
+        assert!(got.obstacle(&(42, -300)));
+        assert!(!got.obstacle(&(43, -300)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_bits() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            #.#.#.#.#
+            .#.#.#.#.
+            #.#.#.#.#
+        ")?;
+
+        let bytes = map.to_bytes();
+        assert_eq!(bytes[MAGIC.len() + 65], BITS);
+
+        let got = GridMap::from_bytes(&bytes)?;
+        assert_eq!(got.obstacles().as_array(), map.obstacles().as_array());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("#..\n..#")?;
+
+        let got = GridMap::from_bytes(&map.clone().with_wrapping(true).to_bytes())?;
+        assert!(got.wrapping());
+        assert!(!GridMap::from_bytes(&map.to_bytes())?.wrapping());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = GridMap::from_ascii("#..\n..#")?.to_bytes();
+
+        assert!(GridMap::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(GridMap::from_bytes(&bytes[1..]).is_err());
+        Ok(())
+    }
+
+    fn header(min: (i64, i64), max: (i64, i64), encoding: u8) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for v in [min.0, min.1, max.0, max.1].iter() {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for v in [0., 0., 1., 0.].iter() {
+            bytes.extend_from_slice(&f64::to_le_bytes(*v));
+        }
+        bytes.extend_from_slice(&[0, encoding]);
+        bytes
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        // The width overflows.
+        assert!(GridMap::from_bytes(&header((i64::MIN, 0), (1, 1), BITS)).is_err());
+        assert!(GridMap::from_bytes(&header((0, i64::MIN), (1, i64::MAX), BITS)).is_err());
+        assert!(GridMap::from_bytes(&header((2, 0), (1, 1), BITS)).is_err());
+
+        // The map is too large for the data.
+        let mut bytes = header((0, 0), (1 << 31, 1 << 31), BITS);
+        assert!(GridMap::from_bytes(&bytes).is_err());
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(GridMap::from_bytes(&bytes).is_err());
+        assert!(GridMap::from_bytes(&header((0, 0), (1 << 31, 1 << 31), RUNS)).is_err());
+
+        // A single run covering a huge map.
+        let mut bytes = header((0, 0), (1 << 31, 1 << 31), RUNS);
+        write_varint(&mut bytes, 1 << 62);
+        match GridMap::from_bytes(&bytes) {
+            Err(err) => assert_eq!(err.to_string(), "invalid map format: binary map: map too large"),
+            Ok(_) => panic!("a huge map was decoded"),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::find_path::PathResult;
+
+        let map = GridMap::with_frame(Grid::new(((0, 0), (1000, 1000)), false), Frame::new((3., 4.), 0.1));
+        let bytes = bincode::serialize(&map)?;
+        assert!(bytes.len() < 100);
+        let got: GridMap = bincode::deserialize(&bytes)?;
+        assert_eq!(got.obstacles().as_array(), map.obstacles().as_array());
+        assert_eq!(got.frame(), map.frame());
+
+        let grid = Grid::with_offset(Array2::from_elem((3, 2), 1.5), (-4, 7));
+        let got: Grid<f64> = bincode::deserialize(&bincode::serialize(&grid)?)?;
+        assert_eq!(got.boundaries(), ((-4, 7), (-1, 9)));
+        assert_eq!(got.get(&(-2, 8)), 1.5);
+
+        let result = PathResult::from(vec![(0, 0), (3, 4)]);
+        let got: PathResult = bincode::deserialize(&bincode::serialize(&result)?)?;
+        assert_eq!(got, PathResult { path: vec![(0, 0), (3, 4)], length: 5. });
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::point::Point2D;
+
 #[derive(Debug)]
 pub(crate) struct NoPathFoundError();
 
@@ -11,4 +13,35 @@ impl fmt::Display for NoPathFoundError {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct OutOfBoundsError(pub(crate) Point2D);
+
+impl std::error::Error for OutOfBoundsError {}
+
+impl fmt::Display for OutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "position {:?} not in bounds", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MapFormatError(pub(crate) String);
+
+impl std::error::Error for MapFormatError {}
+
+impl fmt::Display for MapFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid map format: {}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BlockedSegmentError(pub(crate) Point2D, pub(crate) Point2D);
+
+impl std::error::Error for BlockedSegmentError {}
 
+impl fmt::Display for BlockedSegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "segment from {:?} to {:?} goes through obstacles", self.0, self.1)
+    }
+}
@@ -1,13 +1,34 @@
 use std::cmp::Ordering::Equal;
 use std::collections::BinaryHeap;
-use std::f64::INFINITY;
 
-use crate::errors::NoPathFoundError;
-use crate::grid::Grid;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{NoPathFoundError, OutOfBoundsError};
+use crate::frame::WorldPoint;
+use crate::chunked::ChunkedGrid;
+use crate::grid::{CellStorage, Grid};
 use crate::heap::HeapElement;
-use crate::map::Map;
-use crate::neighbors::get_neighbors;
-use crate::point::{euclidean_distance, Point2D};
+use crate::map::{Bounded, GridMap, Map};
+use crate::point::{is_in_bounds, path_length, Point2D};
+
+/// Size of the chunks of the scores stored by `find_path_chunked`.
+const SCRATCH_CHUNK_SIZE: usize = 64;
+
+/// A path found by a search along with its length, e.g. to cache search results.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PathResult {
+    pub path: Vec<Point2D>,
+    pub length: f64,
+}
+
+impl From<Vec<Point2D>> for PathResult {
+    fn from(path: Vec<Point2D>) -> PathResult {
+        let length = path_length(&path);
+        PathResult { path, length }
+    }
+}
 
 pub fn find_path_impl(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
     if map.obstacle(&start) || map.obstacle(&end) {
@@ -16,17 +37,150 @@ pub fn find_path_impl(map: &impl Map, start: Point2D, end: Point2D) -> Result<Ve
     if map.line_of_sight(&start, &end) {
         return Ok(vec![start, end]);
     }
-    do_find_path(map, end, start)
+    let mut g_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
+    let mut f_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
+    let mut came_from: Grid<Option<Point2D>> = Grid::new(map.boundaries(), None);
+    do_find_path(map, end, start, &mut g_score, &mut f_score, &mut came_from)
+}
+
+/// Same as `find_path_impl` but stores the search scores in chunks allocated as the search
+/// explores the map, so memory scales with the explored area instead of the map size.
+///
+/// Meant for huge or unbounded maps such as `ChunkedMap`.
+pub fn find_path_chunked(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    if map.obstacle(&start) || map.obstacle(&end) {
+        return Err(Box::new(NoPathFoundError()));
+    }
+    if map.line_of_sight(&start, &end) {
+        return Ok(vec![start, end]);
+    }
+    let mut g_score = ChunkedGrid::new(SCRATCH_CHUNK_SIZE, f64::INFINITY);
+    let mut f_score = ChunkedGrid::new(SCRATCH_CHUNK_SIZE, f64::INFINITY);
+    let mut came_from = ChunkedGrid::new(SCRATCH_CHUNK_SIZE, None);
+    do_find_path(map, end, start, &mut g_score, &mut f_score, &mut came_from)
+}
+
+/// A path along with the cells reached by the search that found it.
+pub struct SearchTrace {
+    pub path: Option<Vec<Point2D>>,
+    pub explored: Grid<bool>,
+}
+
+/// Same as `find_path_impl` but also returns the cells reached by the search, which helps to
+/// understand why a search is slow or fails.
+pub fn find_path_traced(map: &impl Map, start: Point2D, end: Point2D) -> SearchTrace {
+    let mut g_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
+    let path = if map.obstacle(&start) || map.obstacle(&end) {
+        None
+    } else if map.line_of_sight(&start, &end) {
+        Some(vec![start, end])
+    } else {
+        let mut f_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
+        let mut came_from: Grid<Option<Point2D>> = Grid::new(map.boundaries(), None);
+        do_find_path(map, end, start, &mut g_score, &mut f_score, &mut came_from).ok()
+    };
+
+    SearchTrace {
+        path,
+        explored: g_score.map(f64::is_finite),
+    }
+}
+
+/// Same as `find_path_impl` but takes and returns positions in the world frame of the map.
+///
+/// Intermediate waypoints are the centers of the cells found by the search, the first and last
+/// waypoints are the exact `start` and `end` positions.
+pub fn find_path_world_impl(map: &GridMap, start: WorldPoint, end: WorldPoint) -> Result<Vec<WorldPoint>, Box<dyn std::error::Error>> {
+    let frame = map.frame();
+    let (start_cell, end_cell) = (frame.world_to_cell(&start), frame.world_to_cell(&end));
+    for cell in [start_cell, end_cell].iter() {
+        if !is_in_bounds(*cell, map.boundaries()) {
+            return Err(Box::new(OutOfBoundsError(*cell)));
+        }
+    }
+
+    let path = find_path_impl(map, start_cell, end_cell)?;
+    let last = path.len() - 1;
+    let path = path.iter()
+        .enumerate()
+        .map(|(i, cell)| match i {
+            0 => start,
+            i if i == last => end,
+            _ => frame.cell_to_world(cell),
+        })
+        .collect();
+    Ok(path)
 }
 
 
-fn do_find_path(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+/// Searches a path from `start` to the nearest of several goals, e.g. the closest charging
+/// station, in a single search whose heuristic is the distance to the nearest goal.
+///
+/// Returns the goal reached along with the path to it. Goals inside obstacles are ignored.
+pub fn find_path_to_any(map: &impl Map, start: Point2D, goals: &[Point2D]) -> Result<(Point2D, Vec<Point2D>), Box<dyn std::error::Error>> {
+    let goals: Vec<Point2D> = goals.iter().copied().filter(|goal| !map.obstacle(goal)).collect();
+    if map.obstacle(&start) || goals.is_empty() {
+        return Err(Box::new(NoPathFoundError()));
+    }
+
     let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::with_capacity(1024);
+    let mut g_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
+    let mut f_score: Grid<f64> = Grid::new(map.boundaries(), f64::INFINITY);
     let mut came_from: Grid<Option<Point2D>> = Grid::new(map.boundaries(), None);
-    let mut g_score: Grid<f64> = Grid::new(map.boundaries(), INFINITY);
-    let mut f_score: Grid<f64> = Grid::new(map.boundaries(), INFINITY);
 
-    let heuristic = |pos: &Point2D| euclidean_distance(pos, &end);
+    // Any-angle paths may be shorter than `Map::heuristic`, which only bounds paths from cell to
+    // adjacent cell, so the search is guided by the straight line.
+    let heuristic = |pos: &Point2D| goals.iter()
+        .map(|goal| map.distance(pos, goal))
+        .fold(f64::INFINITY, f64::min);
+
+    g_score.set(&start, 0.);
+    f_score.set(&start, heuristic(&start));
+    came_from.set(&start, Some(start));
+    open_set.push(HeapElement { position: start, f_score: heuristic(&start) });
+
+    while let Some(HeapElement { position, f_score: elem_f_score }) = open_set.pop() {
+        if elem_f_score > f_score.get(&position) { continue; }
+        if goals.contains(&position) {
+            let mut path = build_path(&came_from, &start, &position);
+            path.reverse();
+            return Ok((position, path));
+        }
+
+        let parent = came_from.get(&position).unwrap();
+        for neighbor in map.neighbors(&position) {
+            if map.obstacle(&neighbor) { continue; }
+
+            // Theta*: skip the current cell when its parent can see the neighbor.
+            let (from, tentative_g_score) = match map.line_of_sight(&parent, &neighbor) {
+                true => (parent, g_score.get(&parent) + map.distance(&parent, &neighbor)),
+                false => (position, g_score.get(&position) + map.distance(&position, &neighbor)),
+            };
+            if tentative_g_score < g_score.get(&neighbor) {
+                g_score.set(&neighbor, tentative_g_score);
+                came_from.set(&neighbor, Some(from));
+                let new_f_score = tentative_g_score + heuristic(&neighbor);
+                f_score.set(&neighbor, new_f_score);
+                open_set.push(HeapElement { position: neighbor, f_score: new_f_score });
+            }
+        }
+    }
+    Err(Box::new(NoPathFoundError()))
+}
+
+
+fn do_find_path(
+    map: &impl Map,
+    start: Point2D,
+    end: Point2D,
+    g_score: &mut impl CellStorage<f64>,
+    f_score: &mut impl CellStorage<f64>,
+    came_from: &mut impl CellStorage<Option<Point2D>>,
+) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::with_capacity(1024);
+
+    // Guided by the straight line rather than `Map::heuristic`, as in `find_path_to_any`.
+    let heuristic = |pos: &Point2D| map.distance(pos, &end);
 
     open_set.push(HeapElement {
         position: start,
@@ -43,21 +197,21 @@ fn do_find_path(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Poin
         let parent = came_from.get(&position).unwrap();
 
         if !map.line_of_sight(&position, &parent) {
-            let (neighbor, neighbor_g_score) = get_neighbors(position, map.boundaries())
+            let (neighbor, neighbor_g_score) = map.neighbors(&position)
                 .map(|pos| (pos, g_score.get(&pos)))
                 .min_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap_or(Equal))
                 .unwrap();
 
-            g_score.set(&position, neighbor_g_score + euclidean_distance(&position, &neighbor) + heuristic(&position));
+            g_score.set(&position, neighbor_g_score + map.distance(&position, &neighbor) + heuristic(&position));
             came_from.set(&position, Some(neighbor));
         }
 
         // VISIT:
-        for neighbor in get_neighbors(position, map.boundaries()) {
+        for neighbor in map.neighbors(&position) {
             if map.obstacle(&neighbor) { continue; }
 
             let parent = came_from.get(&position).unwrap();
-            let tentative_g_score = g_score.get(&parent) + euclidean_distance(&neighbor, &parent) + heuristic(&neighbor);
+            let tentative_g_score = g_score.get(&parent) + map.distance(&neighbor, &parent) + heuristic(&neighbor);
             if tentative_g_score < g_score.get(&neighbor) {
                 came_from.set(&neighbor, Some(parent));
                 g_score.set(&neighbor, tentative_g_score);
@@ -69,14 +223,14 @@ fn do_find_path(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Poin
         };
 
         if position == end {
-            return Ok(build_path(&came_from, &start, &end));
+            return Ok(build_path(came_from, &start, &end));
         }
     }
     Err(Box::new(NoPathFoundError()))
 }
 
 
-fn build_path(came_from: &Grid<Option<Point2D>>, start: &Point2D, end: &Point2D) -> Vec<Point2D> {
+fn build_path(came_from: &impl CellStorage<Option<Point2D>>, start: &Point2D, end: &Point2D) -> Vec<Point2D> {
     let mut path = vec![*end];
     let mut pos = *end;
     while pos != *start {
@@ -91,29 +245,23 @@ fn build_path(came_from: &Grid<Option<Point2D>>, start: &Point2D, end: &Point2D)
 mod tests {
     use ndarray::Array2;
 
-    use crate::map::GridMap;
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::frame::Frame;
 
     use super::*;
 
     #[test]
     fn happy_path_obstacles() -> Result<(), Box<dyn std::error::Error>> {
-        let start = (0, 5);
-        let end = (5, 5);
-
-        let mut arr = Array2::from_elem((6, 6), false);
-        arr[(2, 1)] = true;
-        arr[(2, 2)] = true;
-        arr[(2, 3)] = true;
-        arr[(2, 4)] = true;
-        arr[(2, 5)] = true;
-
-        arr[(4, 0)] = true;
-        arr[(4, 1)] = true;
-        arr[(4, 2)] = true;
-        arr[(4, 3)] = true;
-        arr[(4, 4)] = true;
-
-        let got = find_path_impl(&GridMap::new(Grid::from(arr)), start, end)?;
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#..G
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ....#.
+        ")?;
+
+        let got = find_path_impl(&map, start.unwrap(), goal.unwrap())?;
         assert_eq!(vec![(0, 5), (2, 0), (3, 1), (3, 5), (5, 5)], got);
         Ok(())
     }
@@ -170,4 +318,73 @@ mod tests {
         let result = find_path_impl(&GridMap::new(Grid::from(arr)), start, end);
         assert_eq!(true, result.is_err())
     }
+
+    #[test]
+    fn path_to_nearest_goal() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, .. } = parse_ascii("
+            S.#...
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ....#.
+        ")?;
+        let start = start.unwrap();
+
+        // (5, 0) is closer as the crow flies but behind two walls, (2, 5) is inside a wall.
+        let (goal, path) = find_path_to_any(&map, start, &[(5, 0), (3, 0), (2, 5)])?;
+        assert_eq!(goal, (3, 0));
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&(3, 0)));
+        assert!(path.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+
+        assert_eq!(find_path_to_any(&map, start, &[start, (3, 0)])?, (start, vec![start]));
+        assert!(find_path_to_any(&map, start, &[(2, 5)]).is_err());
+        assert!(find_path_to_any(&map, start, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn traced_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#..G
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ..#.#.
+            ....#.
+        ")?;
+
+        let trace = find_path_traced(&map, start.unwrap(), goal.unwrap());
+        assert_eq!(trace.path, Some(find_path_impl(&map, start.unwrap(), goal.unwrap())?));
+        assert!(trace.explored.get(&start.unwrap()));
+        assert!(trace.explored.get(&goal.unwrap()));
+        assert!(!trace.explored.get(&(2, 5)));
+        Ok(())
+    }
+
+    #[test]
+    fn world_path() -> Result<(), Box<dyn std::error::Error>> {
+        let mut arr = Array2::from_elem((5, 5), false);
+        arr[(2, 1)] = true;
+        arr[(2, 2)] = true;
+        arr[(2, 3)] = true;
+        arr[(2, 4)] = true;
+        let map = GridMap::with_frame(Grid::from(arr), Frame::new((10., -10.), 0.5));
+
+        let got = find_path_world_impl(&map, (10.3, -7.6), (12.1, -7.9))?;
+        assert_eq!(got.first(), Some(&(10.3, -7.6)));
+        assert_eq!(got.last(), Some(&(12.1, -7.9)));
+        assert_eq!(got[1..got.len() - 1].to_vec(), vec![(10.75, -9.75), (11.75, -9.25)]);
+        Ok(())
+    }
+
+    #[test]
+    fn world_path_out_of_bounds() {
+        let arr = Array2::from_elem((5, 5), false);
+        let map = GridMap::with_frame(Grid::from(arr), Frame::new((10., -10.), 0.5));
+
+        let result = find_path_world_impl(&map, (10.3, -7.6), (9.9, -7.9));
+        assert!(result.is_err())
+    }
 }
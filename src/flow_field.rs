@@ -0,0 +1,130 @@
+use std::collections::BinaryHeap;
+
+use crate::grid::Grid;
+use crate::heap::HeapElement;
+use crate::map::{Map, step_cost};
+use crate::point::Point2D;
+
+/// Returns the cost of the shortest path from every cell to `goal`, computed by a single Dijkstra
+/// sweep from `goal`. Obstacles and cells which cannot reach `goal` are infinite.
+pub fn distance_field_impl(map: &impl Map, goal: Point2D) -> Grid<f64> {
+    let mut distances = Grid::new(map.boundaries(), f64::INFINITY);
+    if map.obstacle(&goal) {
+        return distances;
+    }
+
+    let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::with_capacity(1024);
+    distances.set(&goal, 0.);
+    open_set.push(HeapElement { position: goal, f_score: 0. });
+
+    while let Some(HeapElement { position, f_score }) = open_set.pop() {
+        if f_score > distances.get(&position) { continue; }
+
+        for neighbor in map.neighbors(&position) {
+            let distance = f_score + step_cost(map, &position, &neighbor);
+            if distance < distances.get(&neighbor) {
+                distances.set(&neighbor, distance);
+                open_set.push(HeapElement { position: neighbor, f_score: distance });
+            }
+        }
+    }
+    distances
+}
+
+/// Returns the offset between two adjacent cells. Cells of wrapping maps may be adjacent across
+/// the edges of the map, the offset then goes across the edges too.
+fn step_direction((ax, ay): &Point2D, (bx, by): &Point2D) -> Point2D {
+    let wrap = |d: isize| if d.abs() > 1 { -d.signum() } else { d };
+    (wrap(bx - ax), wrap(by - ay))
+}
+
+/// Returns, for every cell, the offset `(dx, dy)` to the adjacent cell to move to in order to
+/// reach the goal of `distances` along a shortest path, so many agents can share a single search.
+///
+/// The offset is `(0, 0)` for the goal, obstacles and cells which cannot reach the goal.
+pub fn flow_field_impl(map: &impl Map, distances: &Grid<f64>) -> Grid<Point2D> {
+    let mut directions = Grid::new(map.boundaries(), (0, 0));
+    let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+    for x in min_x..max_x {
+        for y in min_y..max_y {
+            let cell = (x, y);
+            let distance = distances.get(&cell);
+            if !distance.is_finite() { continue; }
+
+            let next = map.neighbors(&cell)
+                .filter(|n| *n != cell)
+                .map(|n| (n, step_cost(map, &cell, &n) + distances.get(&n)))
+                .filter(|(_, d)| *d <= distance)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((next, _)) = next {
+                directions.set(&cell, step_direction(&cell, &next));
+            }
+        }
+    }
+    directions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::parse_ascii;
+
+    use super::*;
+
+    #[test]
+    fn test_distance_field() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ...#.
+            .#.#.
+            .#...
+        ")?.map;
+
+        let distances = distance_field_impl(&map, (0, 0));
+        assert_eq!(distances.get(&(0, 0)), 0.);
+        assert_eq!(distances.get(&(0, 2)), 2.);
+        assert!((distances.get(&(2, 2)) - (2. + 2f64.sqrt())).abs() < 1e-9);
+        assert!((distances.get(&(4, 2)) - (2. + 4. * 2f64.sqrt())).abs() < 1e-9);
+        assert_eq!(distances.get(&(1, 1)), f64::INFINITY);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flow_field() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            .....
+            .###.
+            ..#..
+        ")?.map;
+        let distances = distance_field_impl(&map, (0, 0));
+        let directions = flow_field_impl(&map, &distances);
+
+        assert_eq!(directions.get(&(0, 0)), (0, 0));
+        assert_eq!(directions.get(&(2, 0)), (0, 0));
+        assert_eq!(directions.get(&(1, 0)), (-1, 0));
+        assert_eq!(directions.get(&(0, 2)), (0, -1));
+
+        // Following the directions from any cell reaches the goal along a shortest path.
+        let mut cell = (4, 0);
+        let mut length = 0.;
+        while cell != (0, 0) {
+            let (dx, dy) = directions.get(&cell);
+            let next = (cell.0 + dx, cell.1 + dy);
+            length += map.distance(&cell, &next);
+            cell = next;
+        }
+        assert!((length - distances.get(&(4, 0))).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping_flow_field() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ......
+            ......
+        ")?.map.with_wrapping(true);
+        let directions = flow_field_impl(&map, &distance_field_impl(&map, (0, 0)));
+
+        assert_eq!(directions.get(&(1, 0)), (-1, 0));
+        assert_eq!(directions.get(&(5, 0)), (1, 0));
+        Ok(())
+    }
+}
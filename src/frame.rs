@@ -0,0 +1,116 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::point::Point2D;
+
+/// A position in world coordinates, usually expressed in metres.
+pub type WorldPoint = (f64, f64);
+
+/// Affine transform between world coordinates and grid cells.
+///
+/// `origin` is the world position of the lower-left corner of cell `(0, 0)`, `resolution` the
+/// size of a cell and `rotation` the yaw (in radians) of the grid relative to the world axes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Frame {
+    origin: WorldPoint,
+    resolution: f64,
+    rotation: f64,
+}
+
+impl Frame {
+    pub fn new(origin: WorldPoint, resolution: f64) -> Frame {
+        Frame {
+            origin,
+            resolution,
+            rotation: 0.,
+        }
+    }
+
+    pub fn with_rotation(self, rotation: f64) -> Frame {
+        Frame { rotation, ..self }
+    }
+
+    pub fn origin(&self) -> WorldPoint {
+        self.origin
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    /// Returns the cell containing the given world position.
+    pub fn world_to_cell(&self, (x, y): &WorldPoint) -> Point2D {
+        let (origin_x, origin_y) = self.origin;
+        let (dx, dy) = (x - origin_x, y - origin_y);
+        let (sin, cos) = self.rotation.sin_cos();
+        let (local_x, local_y) = (dx * cos + dy * sin, dy * cos - dx * sin);
+
+        (
+            (local_x / self.resolution).floor() as isize,
+            (local_y / self.resolution).floor() as isize,
+        )
+    }
+
+    /// Returns the world position of the center of the given cell.
+    pub fn cell_to_world(&self, (x, y): &Point2D) -> WorldPoint {
+        let (origin_x, origin_y) = self.origin;
+        let local_x = (*x as f64 + 0.5) * self.resolution;
+        let local_y = (*y as f64 + 0.5) * self.resolution;
+        let (sin, cos) = self.rotation.sin_cos();
+
+        (
+            origin_x + local_x * cos - local_y * sin,
+            origin_y + local_x * sin + local_y * cos,
+        )
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Frame {
+        Frame::new((0., 0.), 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_close((ax, ay): WorldPoint, (bx, by): WorldPoint) {
+        assert!((ax - bx).abs() < 1e-9 && (ay - by).abs() < 1e-9, "{:?} != {:?}", (ax, ay), (bx, by));
+    }
+
+    #[test]
+    fn test_world_to_cell() {
+        let frame = Frame::new((-10., 5.), 0.5);
+
+        assert_eq!(frame.world_to_cell(&(-10., 5.)), (0, 0));
+        assert_eq!(frame.world_to_cell(&(-9.76, 5.24)), (0, 0));
+        assert_eq!(frame.world_to_cell(&(-9.5, 6.2)), (1, 2));
+        assert_eq!(frame.world_to_cell(&(-10.1, 4.9)), (-1, -1));
+    }
+
+    #[test]
+    fn test_cell_to_world() {
+        let frame = Frame::new((-10., 5.), 0.5);
+
+        assert_close(frame.cell_to_world(&(0, 0)), (-9.75, 5.25));
+        assert_close(frame.cell_to_world(&(1, 2)), (-9.25, 6.25));
+    }
+
+    #[test]
+    fn test_rotated_frame_round_trip() {
+        let frame = Frame::new((1., 2.), 0.25).with_rotation(FRAC_PI_2);
+
+        assert_close(frame.cell_to_world(&(0, 0)), (0.875, 2.125));
+        for cell in [(0, 0), (3, -2), (-7, 11)].iter() {
+            assert_eq!(frame.world_to_cell(&frame.cell_to_world(cell)), *cell);
+        }
+    }
+}
@@ -2,16 +2,53 @@ use std::convert::TryFrom;
 
 use ndarray::{Array, Array2};
 use ndarray::Ix2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::map::Bounded;
 use crate::point::Point2D;
 
+/// Cells storage used by searches for their scores, either dense (`Grid`) or sparse
+/// (`ChunkedGrid`).
+pub trait CellStorage<T> {
+    fn get(&self, point: &Point2D) -> T;
+    fn set(&mut self, point: &Point2D, elem: T);
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(try_from = "RawGrid<T>"))]
 pub struct Grid<T> {
     arr: Array2<T>,
     min: Point2D,
     max: Point2D,
 }
 
+/// Deserialized form of a `Grid`, whose boundaries are checked against the shape of its array.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct RawGrid<T> {
+    arr: Array2<T>,
+    min: Point2D,
+    max: Point2D,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<RawGrid<T>> for Grid<T> {
+    type Error = String;
+
+    fn try_from(RawGrid { arr, min, max }: RawGrid<T>) -> Result<Grid<T>, String> {
+        let shape = arr.shape();
+        let expected = (
+            isize::try_from(shape[0]).ok().and_then(|width| min.0.checked_add(width)),
+            isize::try_from(shape[1]).ok().and_then(|height| min.1.checked_add(height)),
+        );
+        if expected != (Some(max.0), Some(max.1)) {
+            return Err(format!("grid boundaries {:?} do not match its shape {:?}", (min, max), shape));
+        }
+        Ok(Grid { arr, min, max })
+    }
+}
+
 impl<T> Grid<T> where T: Copy {
     pub fn new((min, max): (Point2D, Point2D), elem: T) -> Grid<T> {
         let (min_x, min_y) = min;
@@ -30,15 +67,33 @@ impl<T> Grid<T> where T: Copy {
     }
 
     pub fn from(arr: Array2<T>) -> Grid<T> {
+        Grid::with_offset(arr, (0, 0))
+    }
+
+    /// Creates a grid whose first cell is `min`.
+    pub fn with_offset(arr: Array2<T>, min: Point2D) -> Grid<T> {
         let shape = arr.shape();
-        let max = (shape[0] as isize, shape[1] as isize);
+        let (min_x, min_y) = min;
+        let max = (min_x + shape[0] as isize, min_y + shape[1] as isize);
         Grid {
             arr,
-            min: (0, 0),
+            min,
             max,
         }
     }
 
+    pub fn as_array(&self) -> &Array2<T> {
+        &self.arr
+    }
+
+    pub fn map<U: Copy>(&self, f: impl Fn(T) -> U) -> Grid<U> {
+        Grid {
+            arr: self.arr.map(|elem| f(*elem)),
+            min: self.min,
+            max: self.max,
+        }
+    }
+
     pub fn get(&self, point: &Point2D) -> T {
         self.arr[self.to_ix2(point)]
     }
@@ -61,6 +116,16 @@ impl<T> Grid<T> where T: Copy {
     }
 }
 
+impl<T: Copy> CellStorage<T> for Grid<T> {
+    fn get(&self, point: &Point2D) -> T {
+        Grid::get(self, point)
+    }
+
+    fn set(&mut self, point: &Point2D, elem: T) {
+        Grid::set(self, point, elem)
+    }
+}
+
 impl<T> Bounded for Grid<T> {
     fn boundaries(&self) -> (Point2D, Point2D) {
         (self.min, self.max)
@@ -88,6 +153,21 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_inconsistent_boundaries() -> Result<(), Box<dyn std::error::Error>> {
+        let arr = Array::from_elem((2, 3), 0u8);
+        let valid = bincode::serialize(&(&arr, (-1isize, 1isize), (1isize, 4isize)))?;
+        let grid: Grid<u8> = bincode::deserialize(&valid)?;
+        assert_eq!(grid.boundaries(), ((-1, 1), (1, 4)));
+
+        for (min, max) in [((0, 0), (5, 5)), ((0, 0), (2, 2)), ((isize::MAX, 0), (isize::MIN, 3))].iter() {
+            let bytes = bincode::serialize(&(&arr, min, max))?;
+            assert!(bincode::deserialize::<Grid<u8>>(&bytes).is_err());
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_bounded() -> Result<(), Box<dyn std::error::Error>> {
         let bounds = ((-1, 2), (3, 4));
@@ -0,0 +1,85 @@
+use std::convert::TryFrom;
+
+use ndarray::{Array, Array3};
+use ndarray::Ix3;
+
+use crate::point::Point3D;
+
+/// Same as `Grid` with a third dimension, e.g. for voxel maps.
+#[derive(Clone, Debug)]
+pub struct Grid3D<T> {
+    arr: Array3<T>,
+    min: Point3D,
+    max: Point3D,
+}
+
+impl<T> Grid3D<T> where T: Copy {
+    pub fn new((min, max): (Point3D, Point3D), elem: T) -> Grid3D<T> {
+        let (min_x, min_y, min_z) = min;
+        let (max_x, max_y, max_z) = max;
+        let shape = (
+            usize::try_from(max_x - min_x).unwrap(),
+            usize::try_from(max_y - min_y).unwrap(),
+            usize::try_from(max_z - min_z).unwrap(),
+        );
+        Grid3D {
+            arr: Array::from_elem(shape, elem),
+            min,
+            max,
+        }
+    }
+
+    pub fn from(arr: Array3<T>) -> Grid3D<T> {
+        let shape = arr.shape();
+        let max = (shape[0] as isize, shape[1] as isize, shape[2] as isize);
+        Grid3D {
+            arr,
+            min: (0, 0, 0),
+            max,
+        }
+    }
+
+    pub fn as_array(&self) -> &Array3<T> {
+        &self.arr
+    }
+
+    pub fn boundaries(&self) -> (Point3D, Point3D) {
+        (self.min, self.max)
+    }
+
+    pub fn get(&self, point: &Point3D) -> T {
+        self.arr[self.to_ix3(point)]
+    }
+
+    pub fn set(&mut self, point: &Point3D, elem: T) {
+        let i = self.to_ix3(point);
+        self.arr[i] = elem;
+    }
+
+    fn to_ix3(&self, (x, y, z): &Point3D) -> Ix3 {
+        let (min_x, min_y, min_z) = self.min;
+        Ix3(
+            usize::try_from(x - min_x).unwrap(),
+            usize::try_from(y - min_y).unwrap(),
+            usize::try_from(z - min_z).unwrap(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_set() {
+        let mut grid = Grid3D::new(((-2, 0, 5), (2, 3, 10)), 0);
+
+        grid.set(&(-2, 0, 5), 1);
+        grid.set(&(1, 2, 9), 2);
+
+        assert_eq!(grid.get(&(-2, 0, 5)), 1);
+        assert_eq!(grid.get(&(1, 2, 9)), 2);
+        assert_eq!(grid.get(&(0, 1, 7)), 0);
+        assert_eq!(grid.as_array().dim(), (4, 3, 5));
+    }
+}
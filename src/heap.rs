@@ -1,18 +1,20 @@
 use std::cmp::Ordering;
 use std::cmp::Ordering::Equal;
+use std::collections::HashMap;
+use std::hash::Hash;
 
-pub struct HeapElement<T> {
-    pub f_score: f64,
+pub struct HeapElement<T, K = f64> {
+    pub f_score: K,
     pub position: T,
 }
 
-impl<T> PartialOrd for HeapElement<T> {
+impl<T, K> PartialOrd for HeapElement<T, K> where K: PartialOrd {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.f_score.partial_cmp(&other.f_score)?.reverse())
     }
 }
 
-impl<T> Ord for HeapElement<T> {
+impl<T, K> Ord for HeapElement<T, K> where K: PartialOrd {
     fn cmp(&self, other: &Self) -> Ordering {
         self.f_score.partial_cmp(&other.f_score)
             .unwrap_or_else(|| { Equal })
@@ -20,14 +22,112 @@ impl<T> Ord for HeapElement<T> {
     }
 }
 
-impl<T> Eq for HeapElement<T> {}
+impl<T, K> Eq for HeapElement<T, K> where K: PartialOrd {}
 
-impl<T> PartialEq for HeapElement<T> {
+impl<T, K> PartialEq for HeapElement<T, K> where K: PartialOrd {
     fn eq(&self, other: &Self) -> bool {
         self.f_score.eq(&other.f_score)
     }
 }
 
+/// A min-heap of `HeapElement`s holding each position at most once, whose score can be changed or
+/// removed in place instead of pushing duplicates.
+///
+/// Scores are `f64` unless another ordered key is given, e.g. the two-part keys of D* Lite.
+pub struct DecreaseKeyHeap<T, K = f64> {
+    elements: Vec<HeapElement<T, K>>,
+    indices: HashMap<T, usize>,
+}
+
+impl<T, K> DecreaseKeyHeap<T, K> where T: Copy + Eq + Hash, K: Copy + PartialOrd {
+    pub fn new() -> DecreaseKeyHeap<T, K> {
+        DecreaseKeyHeap {
+            elements: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn peek(&self) -> Option<&HeapElement<T, K>> {
+        self.elements.first()
+    }
+
+    /// Returns the positions in the heap, in no particular order.
+    pub fn positions(&self) -> impl Iterator<Item=T> + '_ {
+        self.elements.iter().map(|element| element.position)
+    }
+
+    /// Inserts `position`, or changes its score if it is already in the heap.
+    pub fn push(&mut self, position: T, f_score: K) {
+        match self.indices.get(&position) {
+            Some(&i) => {
+                let old_f_score = self.elements[i].f_score;
+                self.elements[i].f_score = f_score;
+                if f_score < old_f_score { self.sift_up(i) } else { self.sift_down(i) }
+            }
+            None => {
+                self.elements.push(HeapElement { f_score, position });
+                self.indices.insert(position, self.elements.len() - 1);
+                self.sift_up(self.elements.len() - 1);
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<HeapElement<T, K>> {
+        let position = self.elements.first()?.position;
+        self.remove(&position)
+    }
+
+    pub fn remove(&mut self, position: &T) -> Option<HeapElement<T, K>> {
+        let i = *self.indices.get(position)?;
+        let last = self.elements.len() - 1;
+        self.swap(i, last);
+        self.indices.remove(position);
+        let element = self.elements.pop();
+        if i < last {
+            self.sift_up(i);
+            self.sift_down(i);
+        }
+        element
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.elements.swap(a, b);
+        self.indices.insert(self.elements[a].position, a);
+        self.indices.insert(self.elements[b].position, b);
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.elements[parent].f_score <= self.elements[i].f_score { break; }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut smallest = i;
+            if left < self.elements.len() && self.elements[left].f_score < self.elements[smallest].f_score {
+                smallest = left;
+            }
+            if right < self.elements.len() && self.elements[right].f_score < self.elements[smallest].f_score {
+                smallest = right;
+            }
+            if smallest == i { break; }
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+impl<T, K> Default for DecreaseKeyHeap<T, K> where T: Copy + Eq + Hash, K: Copy + PartialOrd {
+    fn default() -> DecreaseKeyHeap<T, K> {
+        DecreaseKeyHeap::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering::{Greater, Less};
@@ -54,4 +154,24 @@ mod tests {
         assert_eq!(elem_b.partial_cmp(&elem_a), Some(Less));
         assert_eq!(elem_a.partial_cmp(&elem_a), Some(Equal));
     }
+
+    #[test]
+    fn test_decrease_key_heap() {
+        let mut heap = DecreaseKeyHeap::new();
+        for (i, f_score) in [5., 3., 8., 1., 9., 4.].iter().enumerate() {
+            heap.push(i, *f_score);
+        }
+
+        heap.push(4, 0.5);
+        heap.push(3, 7.);
+        assert_eq!(heap.remove(&1).map(|e| e.f_score), Some(3.));
+        assert!(heap.remove(&1).is_none());
+
+        let mut got = Vec::new();
+        while let Some(HeapElement { position, f_score }) = heap.pop() {
+            got.push((position, f_score));
+        }
+        assert_eq!(got, vec![(4, 0.5), (5, 4.), (0, 5.), (3, 7.), (2, 8.)]);
+        assert!(heap.peek().is_none());
+    }
 }
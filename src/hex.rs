@@ -0,0 +1,159 @@
+use crate::grid::Grid;
+use crate::map::{Bounded, Map};
+use crate::point::{is_in_bounds, Point2D};
+
+/// Axial coordinates `(q, r)` of the 6 neighbors of a hexagon.
+const AXIAL_DIRECTIONS: [Point2D; 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+/// Converts "odd-r" offset coordinates `(column, row)`, where odd rows are shifted half a hexagon
+/// to the right, to axial coordinates `(q, r)`.
+pub fn offset_to_axial((column, row): &Point2D) -> Point2D {
+    (column - (row - (row & 1)) / 2, *row)
+}
+
+/// Converts axial coordinates `(q, r)` to "odd-r" offset coordinates `(column, row)`.
+pub fn axial_to_offset((q, r): &Point2D) -> Point2D {
+    (q + (r - (r & 1)) / 2, *r)
+}
+
+/// Returns the number of steps between two hexagons given in axial coordinates.
+pub fn hex_distance((aq, ar): &Point2D, (bq, br): &Point2D) -> isize {
+    let (dq, dr) = (aq - bq, ar - br);
+    (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+}
+
+/// Rounds fractional cube coordinates to the nearest hexagon, in axial coordinates.
+fn cube_round((x, y, z): (f64, f64, f64)) -> Point2D {
+    let (mut q, mut r, s) = (x.round(), y.round(), z.round());
+    let (dq, dr, ds) = ((q - x).abs(), (r - y).abs(), (s - z).abs());
+    if dq > dr && dq > ds {
+        q = -r - s;
+    } else if dr > ds {
+        r = -q - s;
+    }
+    (q as isize, r as isize)
+}
+
+/// Returns the hexagons crossed by the line between two hexagons given in axial coordinates,
+/// both included.
+pub fn hex_line(a: &Point2D, b: &Point2D) -> impl Iterator<Item=Point2D> {
+    let n = hex_distance(a, b);
+    // Nudges the line so it does not run exactly along the edges between two hexagons.
+    let (aq, ar) = (a.0 as f64 + 1e-6, a.1 as f64 + 2e-6);
+    let (bq, br) = (b.0 as f64 + 1e-6, b.1 as f64 + 2e-6);
+    (0..=n).map(move |i| {
+        let t = if n == 0 { 0. } else { i as f64 / n as f64 };
+        let (q, r) = (aq + (bq - aq) * t, ar + (br - ar) * t);
+        cube_round((q, r, -q - r))
+    })
+}
+
+/// A map of pointy-top hexagons stored in a rectangular grid using "odd-r" offset coordinates
+/// `(column, row)`, so the usual `find_path_impl` can be run over hex levels.
+///
+/// Neighbors are the 6 adjacent hexagons and distances are counted in hexagon steps.
+pub struct HexMap {
+    obstacles: Grid<bool>,
+}
+
+impl HexMap {
+    pub fn new(obstacles: Grid<bool>) -> HexMap {
+        HexMap { obstacles }
+    }
+
+    pub fn obstacles(&self) -> &Grid<bool> {
+        &self.obstacles
+    }
+}
+
+impl Map for HexMap {
+    fn obstacle(&self, point: &Point2D) -> bool {
+        self.obstacles.get(point)
+    }
+
+    fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+        let boundaries = self.boundaries();
+        !hex_line(&offset_to_axial(start), &offset_to_axial(end))
+            .map(|hex| axial_to_offset(&hex))
+            .any(|pos| !is_in_bounds(pos, boundaries) || self.obstacles.get(&pos))
+    }
+
+    fn neighbors(&self, point: &Point2D) -> impl Iterator<Item=Point2D> {
+        let (q, r) = offset_to_axial(point);
+        let boundaries = self.boundaries();
+        AXIAL_DIRECTIONS.iter()
+            .map(move |(dq, dr)| axial_to_offset(&(q + dq, r + dr)))
+            .filter(move |pos| is_in_bounds(*pos, boundaries))
+    }
+
+    fn distance(&self, a: &Point2D, b: &Point2D) -> f64 {
+        hex_distance(&offset_to_axial(a), &offset_to_axial(b)) as f64
+    }
+}
+
+impl Bounded for HexMap {
+    fn boundaries(&self) -> (Point2D, Point2D) {
+        self.obstacles.boundaries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ndarray::Array2;
+
+    use crate::find_path::find_path_impl;
+
+    use super::*;
+
+    #[test]
+    fn test_coordinates() {
+        for pos in [(0, 0), (3, 1), (-2, -3), (5, 4)].iter() {
+            assert_eq!(axial_to_offset(&offset_to_axial(pos)), *pos);
+        }
+        assert_eq!(offset_to_axial(&(3, 3)), (2, 3));
+        assert_eq!(hex_distance(&(0, 0), &(2, -1)), 2);
+        assert_eq!(hex_distance(&(0, 0), &(2, 1)), 3);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let map = HexMap::new(Grid::new(((0, 0), (10, 10)), false));
+
+        let even: HashSet<Point2D> = map.neighbors(&(3, 2)).collect();
+        let want: HashSet<Point2D> = [(4, 2), (2, 2), (2, 1), (3, 1), (2, 3), (3, 3)].iter().cloned().collect();
+        assert_eq!(even, want);
+
+        let odd: HashSet<Point2D> = map.neighbors(&(3, 3)).collect();
+        let want: HashSet<Point2D> = [(4, 3), (2, 3), (3, 2), (4, 2), (3, 4), (4, 4)].iter().cloned().collect();
+        assert_eq!(odd, want);
+
+        assert_eq!(map.neighbors(&(0, 0)).count(), 2);
+    }
+
+    #[test]
+    fn test_line() {
+        let line: Vec<Point2D> = hex_line(&(0, 0), &(3, -1)).collect();
+        assert_eq!(line.len(), 4);
+        assert_eq!(line.first(), Some(&(0, 0)));
+        assert_eq!(line.last(), Some(&(3, -1)));
+        assert!(line.windows(2).all(|w| hex_distance(&w[0], &w[1]) == 1));
+    }
+
+    #[test]
+    fn test_find_path() -> Result<(), Box<dyn std::error::Error>> {
+        let mut arr = Array2::from_elem((8, 8), false);
+        for row in 0..7 {
+            arr[(4, row)] = true;
+        }
+        let map = HexMap::new(Grid::from(arr));
+
+        let path = find_path_impl(&map, (0, 0), (7, 0))?;
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(7, 0)));
+        assert!(path.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(path.iter().any(|(_, row)| *row == 7));
+        Ok(())
+    }
+}
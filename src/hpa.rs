@@ -0,0 +1,434 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::errors::NoPathFoundError;
+use crate::grid::Grid;
+use crate::heap::HeapElement;
+use crate::map::{Map, step_cost};
+use crate::point::{is_in_bounds, Point2D};
+
+/// Coordinates of a cluster of an `Hpa`, in clusters.
+type Cluster = (isize, isize);
+
+/// A border between a cluster and the next one, either along x (`true`) or along y (`false`).
+type Border = (Cluster, bool);
+
+/// A Hierarchical Path-Finding A* (HPA*) planner for huge maps.
+///
+/// The map is partitioned into square clusters. Entrances are the cells on both sides of the free
+/// parts of the borders between clusters, along with the diagonal steps across borders which can
+/// only be taken diagonally, e.g. at the corners of clusters. They make an abstract graph whose
+/// edges are either the steps across borders or the precomputed shortest paths between the
+/// entrances of a cluster. Queries search the abstract graph, which is much smaller than the map,
+/// and refine the abstract path inside each cluster crossed, from cell to adjacent cell. Paths are
+/// close to but not always shortest.
+///
+/// On wrapping maps, the seam is not a border between clusters: paths never cross it, and may be
+/// much longer than through the seam, or not found at all.
+pub struct Hpa {
+    cluster_size: isize,
+    boundaries: (Point2D, Point2D),
+    /// Pairs of adjacent cells on both sides of each border.
+    entrances: HashMap<Border, Vec<(Point2D, Point2D)>>,
+    /// Entrances of each cluster.
+    nodes: HashMap<Cluster, Vec<Point2D>>,
+    /// Edges of the abstract graph, with their cost.
+    edges: HashMap<Point2D, Vec<(Point2D, f64)>>,
+}
+
+impl Hpa {
+    /// Partitions `map` into clusters of `cluster_size` by `cluster_size` cells and builds the
+    /// abstract graph.
+    pub fn new(map: &impl Map, cluster_size: usize) -> Hpa {
+        let mut hpa = Hpa {
+            cluster_size: cluster_size.max(1) as isize,
+            boundaries: map.boundaries(),
+            entrances: HashMap::new(),
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+        };
+        let clusters = hpa.clusters();
+        for cluster in clusters.iter() {
+            hpa.build_entrances(map, (*cluster, true));
+            hpa.build_entrances(map, (*cluster, false));
+        }
+        for cluster in clusters.iter() {
+            hpa.build_cluster(map, cluster);
+        }
+        hpa
+    }
+
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size as usize
+    }
+
+    /// Returns the number of nodes of the abstract graph.
+    pub fn node_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Takes into account the cells whose obstacle status changed in `map`. Only the clusters of
+    /// these cells and their borders are rebuilt.
+    pub fn update(&mut self, map: &impl Map, changed: &[Point2D]) {
+        let changed: HashSet<Cluster> = changed.iter().map(|cell| self.cluster_of(cell)).collect();
+        // Diagonal entrances link clusters touching by a corner, so the surrounding clusters and
+        // all their borders are rebuilt.
+        let rebuilt: HashSet<Cluster> = changed.iter().flat_map(surrounding).collect();
+        for cluster in rebuilt.iter() {
+            self.build_entrances(map, (*cluster, true));
+            self.build_entrances(map, (*cluster, false));
+        }
+        for cluster in rebuilt.iter() {
+            if self.contains_cluster(cluster) {
+                self.build_cluster(map, cluster);
+            }
+        }
+    }
+
+    /// Searches a path between two cells, from cell to adjacent cell.
+    pub fn find_path(&self, map: &impl Map, start: Point2D, goal: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        if map.obstacle(&start) || map.obstacle(&goal) {
+            return Err(Box::new(NoPathFoundError()));
+        }
+        let (start_cluster, goal_cluster) = (self.cluster_of(&start), self.cluster_of(&goal));
+        if start_cluster == goal_cluster {
+            let (distances, came_from) = self.search_cluster(map, &start_cluster, start);
+            if distances.get(&goal).is_finite() {
+                return Ok(local_path(&came_from, start, goal));
+            }
+        }
+
+        // Connects the start and the goal to the entrances of their clusters.
+        let (start_distances, _) = self.search_cluster(map, &start_cluster, start);
+        let (goal_distances, _) = self.search_cluster(map, &goal_cluster, goal);
+        let start_edges = self.cluster_edges(&start_cluster, &start_distances);
+        let goal_edges: HashMap<Point2D, f64> = self.cluster_edges(&goal_cluster, &goal_distances).into_iter().collect();
+
+        let abstract_path = self.search_abstract(map, start, goal, &start_edges, &goal_edges)?;
+        Ok(self.refine(map, &abstract_path))
+    }
+
+    fn clusters(&self) -> Vec<Cluster> {
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries;
+        let count = |min: isize, max: isize| (max - min + self.cluster_size - 1) / self.cluster_size;
+        let (width, height) = (count(min_x, max_x), count(min_y, max_y));
+        (0..width).flat_map(|cx| (0..height).map(move |cy| (cx, cy))).collect()
+    }
+
+    fn contains_cluster(&self, (cx, cy): &Cluster) -> bool {
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries;
+        *cx >= 0 && *cy >= 0 && min_x + cx * self.cluster_size < max_x && min_y + cy * self.cluster_size < max_y
+    }
+
+    fn cluster_of(&self, (x, y): &Point2D) -> Cluster {
+        let ((min_x, min_y), _) = self.boundaries;
+        ((x - min_x).div_euclid(self.cluster_size), (y - min_y).div_euclid(self.cluster_size))
+    }
+
+    fn cluster_boundaries(&self, (cx, cy): &Cluster) -> (Point2D, Point2D) {
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries;
+        let (x, y) = (min_x + cx * self.cluster_size, min_y + cy * self.cluster_size);
+        ((x, y), ((x + self.cluster_size).min(max_x), (y + self.cluster_size).min(max_y)))
+    }
+
+    /// Finds the entrances of a border: one pair of cells in the middle of each part of the
+    /// border which is free on both sides, and the diagonal pairs of free cells whose two cells
+    /// have no free cell facing them across the border.
+    ///
+    /// Borders along x also hold the diagonal pairs going to the clusters touching by a corner.
+    fn build_entrances(&mut self, map: &impl Map, border: Border) {
+        let ((cx, cy), along_x) = border;
+        let next = if along_x { (cx + 1, cy) } else { (cx, cy + 1) };
+        let mut entrances = Vec::new();
+
+        if self.contains_cluster(&(cx, cy)) && self.contains_cluster(&next) {
+            let ((x0, y0), (x1, y1)) = self.cluster_boundaries(&(cx, cy));
+            // Cells of both sides of the border, the side of this cluster first.
+            let sides: Vec<(Point2D, Point2D)> = match along_x {
+                true => (y0..y1).map(|y| ((x1 - 1, y), (x1, y))).collect(),
+                false => (x0..x1).map(|x| ((x, y1 - 1), (x, y1))).collect(),
+            };
+            let free = |cell: &Point2D| is_in_bounds(*cell, self.boundaries) && !map.obstacle(cell);
+            let straight = |(a, b): &(Point2D, Point2D)| free(a) && free(b);
+
+            let mut run: Vec<(Point2D, Point2D)> = Vec::new();
+            for pair in sides.iter().map(Some).chain(std::iter::once(None)) {
+                match pair {
+                    Some(pair) if straight(pair) => run.push(*pair),
+                    _ => {
+                        if !run.is_empty() {
+                            entrances.push(run[run.len() / 2]);
+                        }
+                        run.clear();
+                    }
+                }
+            }
+
+            let shift = |(x, y): Point2D, offset: isize| if along_x { (x, y + offset) } else { (x + offset, y) };
+            for (a, b) in sides.iter().copied() {
+                for offset in [-1, 1].iter().copied() {
+                    let diagonal = (a, shift(b, offset));
+                    let facing_a = (a, b);
+                    let facing_b = (shift(a, offset), shift(b, offset));
+                    let corner = !is_in_bounds(diagonal.1, self.cluster_boundaries(&next));
+                    if straight(&diagonal) && !straight(&facing_a) && !straight(&facing_b) && (along_x || !corner) {
+                        entrances.push(diagonal);
+                    }
+                }
+            }
+        }
+        self.entrances.insert(border, entrances);
+    }
+
+    /// Rebuilds the nodes of a cluster from the entrances of its borders, along with their edges.
+    fn build_cluster(&mut self, map: &impl Map, cluster: &Cluster) {
+        for node in self.nodes.remove(cluster).unwrap_or_default() {
+            self.edges.remove(&node);
+        }
+
+        let mut edges: HashMap<Point2D, Vec<(Point2D, f64)>> = HashMap::new();
+        let borders = surrounding(cluster).flat_map(|cluster| [(cluster, true), (cluster, false)]);
+        for border in borders {
+            for (a, b) in self.entrances.get(&border).map(Vec::as_slice).unwrap_or_default() {
+                let (node, other) = match self.cluster_of(a) == *cluster {
+                    true => (*a, *b),
+                    false if self.cluster_of(b) == *cluster => (*b, *a),
+                    false => continue,
+                };
+                edges.entry(node).or_default().push((other, step_cost(map, &node, &other)));
+            }
+        }
+
+        let nodes: Vec<Point2D> = edges.keys().copied().collect();
+        for node in nodes.iter() {
+            let (distances, _) = self.search_cluster(map, cluster, *node);
+            let intra_edges = self.cluster_edges_of(&nodes, &distances);
+            edges.get_mut(node).unwrap().extend(intra_edges.into_iter().filter(|(other, _)| other != node));
+        }
+
+        self.edges.extend(edges);
+        self.nodes.insert(*cluster, nodes);
+    }
+
+    /// Returns the edges from a cell to the reachable entrances of a cluster, given the distances
+    /// from this cell inside the cluster.
+    fn cluster_edges(&self, cluster: &Cluster, distances: &Grid<f64>) -> Vec<(Point2D, f64)> {
+        let nodes = self.nodes.get(cluster).map(Vec::as_slice).unwrap_or_default();
+        self.cluster_edges_of(nodes, distances)
+    }
+
+    fn cluster_edges_of(&self, nodes: &[Point2D], distances: &Grid<f64>) -> Vec<(Point2D, f64)> {
+        nodes.iter()
+            .map(|node| (*node, distances.get(node)))
+            .filter(|(_, distance)| distance.is_finite())
+            .collect()
+    }
+
+    /// Runs a Dijkstra search from `source` which stays inside a cluster.
+    fn search_cluster(&self, map: &impl Map, cluster: &Cluster, source: Point2D) -> (Grid<f64>, Grid<Option<Point2D>>) {
+        let bounds = self.cluster_boundaries(cluster);
+        let mut distances = Grid::new(bounds, f64::INFINITY);
+        let mut came_from = Grid::new(bounds, None);
+        let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::new();
+        distances.set(&source, 0.);
+        open_set.push(HeapElement { position: source, f_score: 0. });
+
+        while let Some(HeapElement { position, f_score }) = open_set.pop() {
+            if f_score > distances.get(&position) { continue; }
+
+            for neighbor in map.neighbors(&position).filter(|n| is_in_bounds(*n, bounds)) {
+                let distance = f_score + step_cost(map, &position, &neighbor);
+                if distance < distances.get(&neighbor) {
+                    distances.set(&neighbor, distance);
+                    came_from.set(&neighbor, Some(position));
+                    open_set.push(HeapElement { position: neighbor, f_score: distance });
+                }
+            }
+        }
+        (distances, came_from)
+    }
+
+    /// Runs A* on the abstract graph, with the start and the goal connected to the entrances of
+    /// their clusters.
+    fn search_abstract(
+        &self,
+        map: &impl Map,
+        start: Point2D,
+        goal: Point2D,
+        start_edges: &[(Point2D, f64)],
+        goal_edges: &HashMap<Point2D, f64>,
+    ) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        let mut open_set: BinaryHeap<HeapElement<Point2D>> = BinaryHeap::new();
+        let mut g_score: HashMap<Point2D, f64> = HashMap::new();
+        let mut came_from: HashMap<Point2D, Point2D> = HashMap::new();
+        g_score.insert(start, 0.);
+        open_set.push(HeapElement { position: start, f_score: map.heuristic(&start, &goal) });
+
+        while let Some(HeapElement { position, f_score }) = open_set.pop() {
+            let g = g_score[&position];
+            if f_score > g + map.heuristic(&position, &goal) { continue; }
+            if position == goal {
+                let mut path = vec![goal];
+                while let Some(previous) = came_from.get(path.last().unwrap()) {
+                    path.push(*previous);
+                }
+                path.reverse();
+                return Ok(path);
+            }
+
+            let own_edges = self.edges.get(&position).map(Vec::as_slice).unwrap_or_default();
+            let extra_edges = if position == start { start_edges } else { &[] };
+            let to_goal = goal_edges.get(&position).map(|cost| (goal, *cost));
+            for (neighbor, cost) in own_edges.iter().chain(extra_edges.iter()).copied().chain(to_goal) {
+                let tentative_g_score = g + cost;
+                if tentative_g_score < g_score.get(&neighbor).copied().unwrap_or(f64::INFINITY) {
+                    g_score.insert(neighbor, tentative_g_score);
+                    came_from.insert(neighbor, position);
+                    open_set.push(HeapElement { position: neighbor, f_score: tentative_g_score + map.heuristic(&neighbor, &goal) });
+                }
+            }
+        }
+        Err(Box::new(NoPathFoundError()))
+    }
+
+    /// Turns an abstract path into a path from cell to adjacent cell.
+    fn refine(&self, map: &impl Map, abstract_path: &[Point2D]) -> Vec<Point2D> {
+        let mut path = vec![abstract_path[0]];
+        for w in abstract_path.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let cluster = self.cluster_of(&a);
+            if cluster == self.cluster_of(&b) {
+                let (_, came_from) = self.search_cluster(map, &cluster, a);
+                path.extend(local_path(&came_from, a, b).into_iter().skip(1));
+            } else {
+                path.push(b);
+            }
+        }
+        path
+    }
+}
+
+/// Returns a cluster and the 8 clusters around it.
+fn surrounding((cx, cy): &Cluster) -> impl Iterator<Item=Cluster> {
+    let (cx, cy) = (*cx, *cy);
+    (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| (cx + dx, cy + dy)))
+}
+
+fn local_path(came_from: &Grid<Option<Point2D>>, start: Point2D, end: Point2D) -> Vec<Point2D> {
+    let mut path = vec![end];
+    let mut pos = end;
+    while pos != start {
+        pos = came_from.get(&pos).unwrap();
+        path.push(pos);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::parse_ascii;
+    use crate::dstar_lite::DStarLite;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    fn map() -> Result<GridMap, Box<dyn std::error::Error>> {
+        Ok(parse_ascii("
+            ............
+            .####.####..
+            .#.......#..
+            .#.###.#.#..
+            ...#...#....
+            ####.#####.#
+            ...#........
+            .#.#.######.
+            .#..........
+        ")?.map)
+    }
+
+    fn cost(map: &impl Map, path: &[Point2D]) -> f64 {
+        path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum()
+    }
+
+    fn assert_valid(map: &GridMap, path: &[Point2D], start: Point2D, goal: Point2D) {
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| map.neighbors(&w[0]).any(|n| n == w[1]) && !map.obstacle(&w[1])));
+    }
+
+    #[test]
+    fn test_find_path() -> Result<(), Box<dyn std::error::Error>> {
+        let map = map()?;
+        let hpa = Hpa::new(&map, 4);
+        assert!(hpa.node_count() > 0);
+
+        for (start, goal) in [((0, 0), (11, 8)), ((2, 6), (8, 2)), ((0, 8), (3, 8)), ((5, 6), (4, 3))].iter() {
+            let path = hpa.find_path(&map, *start, *goal)?;
+            assert_valid(&map, &path, *start, *goal);
+
+            let shortest = DStarLite::new(&map, *start, *goal).plan(&map)?;
+            assert!(cost(&map, &path) <= 1.5 * cost(&map, &shortest));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_update() -> Result<(), Box<dyn std::error::Error>> {
+        let map = map()?;
+        let mut hpa = Hpa::new(&map, 4);
+        let (start, goal) = ((0, 0), (0, 8));
+        assert!(hpa.find_path(&map, start, goal).is_ok());
+
+        // Closes the left passage through the middle wall, the path has to go through the right one.
+        let mut obstacles: Grid<bool> = map.obstacles().clone();
+        obstacles.set(&(4, 3), true);
+        let closed = GridMap::new(obstacles.clone());
+        hpa.update(&closed, &[(4, 3)]);
+        let path = hpa.find_path(&closed, start, goal)?;
+        assert_valid(&closed, &path, start, goal);
+        assert!(path.contains(&(10, 3)));
+
+        // Also closes the right passage, the top part is no longer reachable.
+        obstacles.set(&(10, 3), true);
+        let closed = GridMap::new(obstacles);
+        hpa.update(&closed, &[(10, 3)]);
+        assert!(hpa.find_path(&closed, start, goal).is_err());
+
+        let fresh = Hpa::new(&closed, 4);
+        assert_eq!(fresh.node_count(), hpa.node_count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagonal_entrances() -> Result<(), Box<dyn std::error::Error>> {
+        // The walls on both sides of the border between the two clusters can only be crossed
+        // diagonally, from (3, 0) to (4, 1).
+        let mut obstacles = Grid::new(((0, 0), (8, 4)), false);
+        for y in 1..4 {
+            obstacles.set(&(3, y), true);
+        }
+        for y in [0, 2, 3].iter() {
+            obstacles.set(&(4, *y), true);
+        }
+        let map = GridMap::new(obstacles.clone());
+        let hpa = Hpa::new(&map, 4);
+
+        let path = hpa.find_path(&map, (0, 3), (7, 3))?;
+        assert_valid(&map, &path, (0, 3), (7, 3));
+        assert!(path.windows(2).any(|w| w == [(3, 0), (4, 1)]));
+
+        // Same across the corner of four clusters, from (3, 3) to (4, 4).
+        let mut obstacles = Grid::new(((0, 0), (8, 8)), true);
+        for (x, y) in [(0, 0), (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6), (7, 7)].iter() {
+            obstacles.set(&(*x, *y), false);
+        }
+        let map = GridMap::new(obstacles);
+        let mut hpa = Hpa::new(&map, 4);
+        assert_eq!(hpa.find_path(&map, (0, 0), (7, 7))?.len(), 8);
+
+        hpa.update(&map, &[(5, 5)]);
+        assert_eq!(hpa.node_count(), Hpa::new(&map, 4).node_count());
+        assert_eq!(hpa.find_path(&map, (0, 0), (7, 7))?.len(), 8);
+        Ok(())
+    }
+}
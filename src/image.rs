@@ -0,0 +1,243 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+
+use bresenham::Bresenham;
+use ndarray::Array2;
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::errors::MapFormatError;
+use crate::grid::Grid;
+use crate::map::{Bounded, GridMap};
+use crate::point::Point2D;
+
+type Rgb = (u8, u8, u8);
+
+const FREE_COLOR: Rgb = (255, 255, 255);
+const OBSTACLE_COLOR: Rgb = (100, 100, 100);
+const EXPLORED_COLOR: Rgb = (170, 200, 255);
+const PATH_COLOR: Rgb = (255, 0, 0);
+
+fn format_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("png: {}", msg)))
+}
+
+/// Loads obstacles from an image, pixels darker than `threshold` being obstacles.
+///
+/// Colors are converted to grayscale and transparent pixels are blended over white. The top row of
+/// the image is the row with the highest `y`.
+pub fn load_png(path: impl AsRef<Path>, threshold: u8) -> Result<GridMap, Box<dyn std::error::Error>> {
+    let mut decoder = Decoder::new(File::open(path)?);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info()?;
+    if info.width == 0 || info.height == 0 {
+        return Err(format_error("empty image"));
+    }
+    let mut buf = vec![0; info.line_size * info.height as usize];
+    reader.next_frame(&mut buf)?;
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let samples = info.line_size / width;
+    let mut obstacles = Array2::from_elem((width, height), false);
+    for (row, line) in buf.chunks(info.line_size).enumerate() {
+        for (x, pixel) in line.chunks(samples).take(width).enumerate() {
+            let luminance = match (info.color_type, pixel) {
+                (ColorType::Grayscale, [v]) => u32::from(*v),
+                (ColorType::GrayscaleAlpha, [v, a]) => blend_over_white(u32::from(*v), *a),
+                (ColorType::RGB, [r, g, b]) => gray(*r, *g, *b),
+                (ColorType::RGBA, [r, g, b, a]) => blend_over_white(gray(*r, *g, *b), *a),
+                _ => return Err(format_error(&format!("unsupported color type {:?}", info.color_type))),
+            };
+            obstacles[(x, height - 1 - row)] = luminance < u32::from(threshold);
+        }
+    }
+
+    Ok(GridMap::new(Grid::from(obstacles)))
+}
+
+fn gray(r: u8, g: u8, b: u8) -> u32 {
+    (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000
+}
+
+fn blend_over_white(v: u32, alpha: u8) -> u32 {
+    let alpha = u32::from(alpha);
+    (v * alpha + 255 * (255 - alpha)) / 255
+}
+
+fn cell_color(map: &GridMap, explored: Option<&Grid<bool>>, cell: &Point2D) -> Rgb {
+    if map.obstacles().get(cell) {
+        OBSTACLE_COLOR
+    } else if explored.is_some_and(|explored| explored.get(cell)) {
+        EXPLORED_COLOR
+    } else {
+        FREE_COLOR
+    }
+}
+
+/// Draws the map as a PNG image, each cell being a square of `cell_size` pixels, with the explored
+/// cells (see `find_path_traced`) and the path on top of the obstacles.
+pub fn render_png(map: &GridMap, explored: Option<&Grid<bool>>, path: &[Point2D], cell_size: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+    let width = (max_x - min_x) as usize * cell_size;
+    let height = (max_y - min_y) as usize * cell_size;
+    let mut pixels = vec![0u8; width * height * 3];
+
+    let mut put_pixel = |(px, py): (isize, isize), (r, g, b): Rgb| {
+        if px >= 0 && py >= 0 && (px as usize) < width && (py as usize) < height {
+            let i = (py as usize * width + px as usize) * 3;
+            pixels[i..i + 3].copy_from_slice(&[r, g, b]);
+        }
+    };
+    let cell_size = cell_size as isize;
+    let to_pixel = |(x, y): &Point2D| {
+        ((x - min_x) * cell_size + cell_size / 2, (max_y - 1 - y) * cell_size + cell_size / 2)
+    };
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let color = cell_color(map, explored, &(x, y));
+            let (left, top) = ((x - min_x) * cell_size, (max_y - 1 - y) * cell_size);
+            for py in top..top + cell_size {
+                for px in left..left + cell_size {
+                    put_pixel((px, py), color);
+                }
+            }
+        }
+    }
+
+    for w in path.windows(2) {
+        for pixel in Bresenham::new(to_pixel(&w[0]), to_pixel(&w[1])) {
+            put_pixel(pixel, PATH_COLOR);
+        }
+    }
+    for cell in path.iter() {
+        let (cx, cy) = to_pixel(cell);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                put_pixel((cx + dx, cy + dy), PATH_COLOR);
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut bytes, width as u32, height as u32);
+        encoder.set_color(ColorType::RGB);
+        encoder.set_depth(BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+    }
+    Ok(bytes)
+}
+
+/// Same as `render_png`, as an SVG document.
+pub fn render_svg(map: &GridMap, explored: Option<&Grid<bool>>, path: &[Point2D], cell_size: usize) -> String {
+    let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+    let (width, height) = ((max_x - min_x) as usize * cell_size, (max_y - min_y) as usize * cell_size);
+    let cell_size = cell_size as isize;
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#, width, height).unwrap();
+    writeln!(svg, r#"<rect width="{}" height="{}" fill="{}"/>"#, width, height, hex(FREE_COLOR)).unwrap();
+
+    // Consecutive cells of the same color on a row are merged in a single rectangle.
+    for y in min_y..max_y {
+        let mut x = min_x;
+        while x < max_x {
+            let color = cell_color(map, explored, &(x, y));
+            let start = x;
+            while x < max_x && cell_color(map, explored, &(x, y)) == color {
+                x += 1;
+            }
+            if color != FREE_COLOR {
+                writeln!(
+                    svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    (start - min_x) * cell_size, (max_y - 1 - y) * cell_size, (x - start) * cell_size, cell_size, hex(color),
+                ).unwrap();
+            }
+        }
+    }
+
+    if !path.is_empty() {
+        let points: Vec<String> = path.iter()
+            .map(|(x, y)| {
+                let px = (x - min_x) as f64 * cell_size as f64 + cell_size as f64 / 2.;
+                let py = (max_y - 1 - y) as f64 * cell_size as f64 + cell_size as f64 / 2.;
+                format!("{},{}", px, py)
+            })
+            .collect();
+        writeln!(
+            svg, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+            points.join(" "), hex(PATH_COLOR), (cell_size as f64 / 4.).max(1.),
+        ).unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::map::Map;
+
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            ..#
+            #..
+        ")?;
+        let file = env::temp_dir().join(format!("grid_pathfinding_image_{}.png", std::process::id()));
+
+        fs::write(&file, render_png(&map, None, &[], 3)?)?;
+        let got = load_png(&file, 128)?;
+        fs::remove_file(&file)?;
+
+        assert_eq!(got.boundaries(), ((0, 0), (9, 6)));
+        assert!(got.obstacle(&(6, 5)));
+        assert!(got.obstacle(&(8, 3)));
+        assert!(got.obstacle(&(0, 0)));
+        assert!(!got.obstacle(&(3, 5)));
+        assert!(!got.obstacle(&(8, 2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_png() -> Result<(), Box<dyn std::error::Error>> {
+        let file = env::temp_dir().join(format!("grid_pathfinding_empty_{}.png", std::process::id()));
+        let mut bytes = Vec::new();
+        Encoder::new(&mut bytes, 1, 4).write_header()?.write_image_data(&[0; 4])?;
+        // Encoders refuse empty images, so the width of the header is set to 0 afterwards.
+        bytes[16..20].copy_from_slice(&0u32.to_be_bytes());
+        let crc = bytes[12..29].iter().fold(!0u32, |crc, byte| {
+            (0..8).fold(crc ^ u32::from(*byte), |crc, _| (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg()))
+        });
+        bytes[29..33].copy_from_slice(&(!crc).to_be_bytes());
+        fs::write(&file, bytes)?;
+        let got = load_png(&file, 128);
+        fs::remove_file(&file)?;
+
+        assert!(got.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_svg() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            ###.
+            ....
+        ")?;
+
+        let svg = render_svg(&map, None, &[(0, 0), (3, 1)], 10);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">"#));
+        assert!(svg.contains(r##"<rect x="0" y="0" width="30" height="10" fill="#646464"/>"##));
+        assert!(svg.contains(r#"<polyline points="5,15 35,5""#));
+        Ok(())
+    }
+}
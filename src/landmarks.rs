@@ -0,0 +1,252 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::flow_field::distance_field_impl;
+use crate::grid::Grid;
+use crate::map::{Bounded, Map};
+use crate::point::Point2D;
+
+/// Precomputed costs of the shortest paths from a few landmark cells to every cell of a map, which
+/// give a better heuristic than the straight line on maze-like maps (ALT).
+///
+/// By the triangle inequality, the cost of a path from `a` to `b` is at least
+/// `|cost(L, b) - cost(L, a)|` for any landmark `L`. Costs are those of paths from cell to
+/// adjacent cell, which may be longer than any-angle paths, so only the searches from cell to
+/// adjacent cell are guided by landmarks: the any-angle searches keep the straight line.
+///
+/// Tables must be computed again when the obstacles of the map change. In particular, they are
+/// not updated by `DStarLite::update`, and a `LandmarkMap` built on stale tables may overestimate
+/// the costs around the changed cells.
+///
+/// With the `serde` feature, landmarks are serializable so the tables of a static map can be
+/// computed offline.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Landmarks {
+    positions: Vec<Point2D>,
+    costs: Vec<Grid<f64>>,
+}
+
+impl Landmarks {
+    /// Picks `count` landmarks spread over the free cells of `map`: each landmark is the cell the
+    /// furthest from the previous ones, which makes landmarks end up in dead ends and corners.
+    pub fn new(map: &impl Map, count: usize) -> Landmarks {
+        let mut landmarks = Landmarks { positions: Vec::new(), costs: Vec::new() };
+        let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+        let first_free = (min_x..max_x)
+            .flat_map(|x| (min_y..max_y).map(move |y| (x, y)))
+            .find(|cell| !map.obstacle(cell));
+        let first_free = match first_free {
+            Some(cell) => cell,
+            None => return landmarks,
+        };
+
+        // Distance of each cell to the nearest landmark, starting from an arbitrary free cell.
+        let mut nearest = distance_field_impl(map, first_free);
+        for _ in 0..count {
+            let furthest = (min_x..max_x)
+                .flat_map(|x| (min_y..max_y).map(move |y| (x, y)))
+                .filter(|cell| nearest.get(cell).is_finite() && nearest.get(cell) > 0.)
+                .max_by(|a, b| nearest.get(a).total_cmp(&nearest.get(b)));
+            let position = match furthest {
+                Some(cell) => cell,
+                None => break,
+            };
+            landmarks.add(map, position);
+
+            let costs = landmarks.costs.last().unwrap();
+            let cells = (min_x..max_x).flat_map(|x| (min_y..max_y).map(move |y| (x, y)));
+            for cell in cells {
+                let cost = match landmarks.positions.len() {
+                    1 => costs.get(&cell),
+                    _ => nearest.get(&cell).min(costs.get(&cell)),
+                };
+                nearest.set(&cell, cost);
+            }
+        }
+        landmarks
+    }
+
+    /// Uses the given cells of `map` as landmarks.
+    pub fn with_positions(map: &impl Map, positions: &[Point2D]) -> Landmarks {
+        let mut landmarks = Landmarks { positions: Vec::new(), costs: Vec::new() };
+        for position in positions.iter() {
+            landmarks.add(map, *position);
+        }
+        landmarks
+    }
+
+    fn add(&mut self, map: &impl Map, position: Point2D) {
+        self.positions.push(position);
+        self.costs.push(distance_field_impl(map, position));
+    }
+
+    pub fn positions(&self) -> &[Point2D] {
+        &self.positions
+    }
+
+    /// Returns the lower bound of the cost of a path between two cells given by the landmarks,
+    /// 0 if no landmark reaches both cells.
+    pub fn heuristic(&self, a: &Point2D, b: &Point2D) -> f64 {
+        self.costs.iter()
+            .map(|costs| (costs.get(a), costs.get(b)))
+            .filter(|(from_a, from_b)| from_a.is_finite() && from_b.is_finite())
+            .map(|(from_a, from_b)| (from_a - from_b).abs())
+            .fold(0., f64::max)
+    }
+}
+
+/// A map whose searches are guided by landmarks, on top of its own heuristic.
+pub struct LandmarkMap<'a, M: Map> {
+    map: &'a M,
+    landmarks: &'a Landmarks,
+}
+
+impl<'a, M: Map> LandmarkMap<'a, M> {
+    /// `landmarks` must have been computed on `map`.
+    pub fn new(map: &'a M, landmarks: &'a Landmarks) -> LandmarkMap<'a, M> {
+        LandmarkMap { map, landmarks }
+    }
+}
+
+impl<M: Map> Map for LandmarkMap<'_, M> {
+    fn obstacle(&self, point: &Point2D) -> bool {
+        self.map.obstacle(point)
+    }
+
+    fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+        self.map.line_of_sight(start, end)
+    }
+
+    fn neighbors(&self, point: &Point2D) -> impl Iterator<Item=Point2D> {
+        self.map.neighbors(point)
+    }
+
+    fn distance(&self, a: &Point2D, b: &Point2D) -> f64 {
+        self.map.distance(a, b)
+    }
+
+    fn heuristic(&self, a: &Point2D, b: &Point2D) -> f64 {
+        self.map.heuristic(a, b).max(self.landmarks.heuristic(a, b))
+    }
+}
+
+impl<M: Map> Bounded for LandmarkMap<'_, M> {
+    fn boundaries(&self) -> (Point2D, Point2D) {
+        self.map.boundaries()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::bidirectional::find_path_bidirectional;
+    use crate::find_path::{find_path_impl, find_path_to_any};
+    use crate::map::GridMap;
+
+    use super::*;
+
+    /// A map which counts the cells expanded by the searches.
+    struct CountingMap {
+        map: GridMap,
+        expanded: Cell<usize>,
+    }
+
+    impl Map for CountingMap {
+        fn obstacle(&self, point: &Point2D) -> bool {
+            self.map.obstacle(point)
+        }
+
+        fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+            self.map.line_of_sight(start, end)
+        }
+
+        fn neighbors(&self, point: &Point2D) -> impl Iterator<Item=Point2D> {
+            self.expanded.set(self.expanded.get() + 1);
+            self.map.neighbors(point)
+        }
+    }
+
+    impl Bounded for CountingMap {
+        fn boundaries(&self) -> (Point2D, Point2D) {
+            self.map.boundaries()
+        }
+    }
+
+    fn snail() -> Result<AsciiMap, Box<dyn std::error::Error>> {
+        parse_ascii("
+            S.#...#...#.
+            ..#.#.#.#.#.
+            ..#.#.#.#.#.
+            ..#.#.#.#.#.
+            ....#...#..G
+        ")
+    }
+
+    #[test]
+    fn test_admissible_heuristic() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, goal, .. } = snail()?;
+        let goal = goal.unwrap();
+        let landmarks = Landmarks::new(&map, 3);
+        assert_eq!(landmarks.positions().len(), 3);
+
+        let costs = distance_field_impl(&map, goal);
+        let guided = LandmarkMap::new(&map, &landmarks);
+        let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+        for cell in (min_x..max_x).flat_map(|x| (min_y..max_y).map(move |y| (x, y))) {
+            if costs.get(&cell).is_finite() {
+                assert!(guided.heuristic(&cell, &goal) <= costs.get(&cell) + 1e-9);
+                assert!(guided.heuristic(&cell, &goal) >= map.heuristic(&cell, &goal));
+            }
+        }
+        assert!(guided.heuristic(&(0, 0), &goal) > 1.5 * map.heuristic(&(0, 0), &goal));
+        Ok(())
+    }
+
+    #[test]
+    fn test_guided_searches() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = snail()?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let landmarks = Landmarks::with_positions(&map, &[(11, 4), (0, 0)]);
+        let guided = LandmarkMap::new(&map, &landmarks);
+        let cost = |path: &[Point2D]| path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum::<f64>();
+
+        let path = find_path_bidirectional(&guided, start, goal)?;
+        assert!((cost(&path) - cost(&find_path_bidirectional(&map, start, goal)?)).abs() < 1e-9);
+
+        let counting = CountingMap { map: map.clone(), expanded: Cell::new(0) };
+        find_path_bidirectional(&counting, start, goal)?;
+        let plain = counting.expanded.replace(0);
+        find_path_bidirectional(&LandmarkMap::new(&counting, &landmarks), start, goal)?;
+        assert!(counting.expanded.get() < plain);
+        Ok(())
+    }
+
+    #[test]
+    fn test_any_angle_searches() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = snail()?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let landmarks = Landmarks::new(&map, 3);
+        let guided = LandmarkMap::new(&map, &landmarks);
+        let cost = |path: &[Point2D]| path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum::<f64>();
+
+        let path = find_path_impl(&guided, start, goal)?;
+        assert!((cost(&path) - cost(&find_path_impl(&map, start, goal)?)).abs() < 1e-9);
+        let (_, path) = find_path_to_any(&guided, start, &[goal])?;
+        assert!((cost(&path) - cost(&find_path_to_any(&map, start, &[goal])?.1)).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(), Box<dyn std::error::Error>> {
+        let map = snail()?.map;
+        let landmarks = Landmarks::new(&map, 2);
+        let got: Landmarks = bincode::deserialize(&bincode::serialize(&landmarks)?)?;
+        assert_eq!(got.positions(), landmarks.positions());
+        assert_eq!(got.heuristic(&(0, 0), &(11, 0)), landmarks.heuristic(&(0, 0), &(11, 0)));
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::errors::NoPathFoundError;
+use crate::grid::Grid;
+use crate::heap::HeapElement;
+use crate::map::{Bounded, GridMap, Map};
+use crate::point::Point2D;
+
+/// A cell of a `LayeredMap`: the index of its layer and its position in this layer.
+pub type LayerPoint = (usize, Point2D);
+
+/// A map made of several `GridMap` layers, e.g. the floors of a building, connected by portals
+/// such as stairs or elevators.
+///
+/// Portals are edges between two cells of any layers, with their own cost, instead of the
+/// distance between the cells.
+pub struct LayeredMap {
+    layers: Vec<GridMap>,
+    portals: HashMap<LayerPoint, Vec<(LayerPoint, f64)>>,
+}
+
+impl LayeredMap {
+    pub fn new(layers: Vec<GridMap>) -> LayeredMap {
+        LayeredMap {
+            layers,
+            portals: HashMap::new(),
+        }
+    }
+
+    pub fn layers(&self) -> &[GridMap] {
+        &self.layers
+    }
+
+    /// Adds a portal which can only be taken from `from` to `to`.
+    pub fn add_portal(&mut self, from: LayerPoint, to: LayerPoint, cost: f64) {
+        self.portals.entry(from).or_default().push((to, cost));
+    }
+
+    /// Adds a portal which can be taken both ways, with the same cost.
+    pub fn add_two_way_portal(&mut self, a: LayerPoint, b: LayerPoint, cost: f64) {
+        self.add_portal(a, b, cost);
+        self.add_portal(b, a, cost);
+    }
+
+    /// Returns the cells reached by taking the portals of `from`, along with their cost.
+    pub fn portals(&self, from: &LayerPoint) -> &[(LayerPoint, f64)] {
+        self.portals.get(from).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn obstacle(&self, (layer, point): &LayerPoint) -> bool {
+        self.layers[*layer].obstacle(point)
+    }
+}
+
+/// Searches an any-angle path between two cells of a layered map, possibly on different layers.
+///
+/// Inside a layer, waypoints are only kept where there is no line of sight; each portal taken
+/// appears as two consecutive waypoints, its entry and its exit. Portal costs must not be negative.
+pub fn find_path_layered_impl(map: &LayeredMap, start: LayerPoint, end: LayerPoint) -> Result<Vec<LayerPoint>, Box<dyn std::error::Error>> {
+    if map.obstacle(&start) || map.obstacle(&end) {
+        return Err(Box::new(NoPathFoundError()));
+    }
+
+    let mut open_set: BinaryHeap<HeapElement<LayerPoint>> = BinaryHeap::with_capacity(1024);
+    let mut g_score: Vec<Grid<f64>> = map.layers.iter().map(|l| Grid::new(l.boundaries(), f64::INFINITY)).collect();
+    let mut f_score: Vec<Grid<f64>> = g_score.clone();
+    let mut came_from: Vec<Grid<Option<LayerPoint>>> = map.layers.iter().map(|l| Grid::new(l.boundaries(), None)).collect();
+
+    // Portals may lead anywhere, so a path to the goal is either in its layer, and bounded by the
+    // heuristic of the layer, or takes a portal, and costs at least the cheapest portal.
+    let cheapest_portal = map.portals.values()
+        .flatten()
+        .map(|(_, cost)| *cost)
+        .fold(f64::INFINITY, f64::min);
+    let heuristic = |(layer, pos): &LayerPoint| match *layer == end.0 {
+        true => map.layers[end.0].heuristic(pos, &end.1).min(cheapest_portal),
+        false => 0.,
+    };
+
+    g_score[start.0].set(&start.1, 0.);
+    f_score[start.0].set(&start.1, heuristic(&start));
+    came_from[start.0].set(&start.1, Some(start));
+    open_set.push(HeapElement { position: start, f_score: heuristic(&start) });
+
+    while let Some(HeapElement { position, f_score: elem_f_score }) = open_set.pop() {
+        let (layer, pos) = position;
+        if elem_f_score > f_score[layer].get(&pos) { continue; }
+        if position == end {
+            return Ok(build_path(&came_from, &start, &end));
+        }
+
+        let grid_map = &map.layers[layer];
+        let g = g_score[layer].get(&pos);
+        let parent = came_from[layer].get(&pos).unwrap();
+        let parent_g = g_score[parent.0].get(&parent.1);
+
+        let mut relax = |to: LayerPoint, from: LayerPoint, tentative_g_score: f64| {
+            let (to_layer, to_pos) = to;
+            if tentative_g_score < g_score[to_layer].get(&to_pos) {
+                g_score[to_layer].set(&to_pos, tentative_g_score);
+                came_from[to_layer].set(&to_pos, Some(from));
+                let new_f_score = tentative_g_score + heuristic(&to);
+                f_score[to_layer].set(&to_pos, new_f_score);
+                open_set.push(HeapElement { position: to, f_score: new_f_score });
+            }
+        };
+
+        for neighbor in grid_map.neighbors(&pos) {
+            if grid_map.obstacle(&neighbor) { continue; }
+
+            let (parent_layer, parent_pos) = parent;
+            if parent_layer == layer && grid_map.line_of_sight(&parent_pos, &neighbor) {
+                relax((layer, neighbor), parent, parent_g + grid_map.distance(&parent_pos, &neighbor));
+            } else {
+                relax((layer, neighbor), position, g + grid_map.distance(&pos, &neighbor));
+            }
+        }
+
+        for (exit, cost) in map.portals(&position) {
+            if map.obstacle(exit) { continue; }
+            relax(*exit, position, g + cost);
+        }
+    }
+    Err(Box::new(NoPathFoundError()))
+}
+
+fn build_path(came_from: &[Grid<Option<LayerPoint>>], start: &LayerPoint, end: &LayerPoint) -> Vec<LayerPoint> {
+    let mut path = vec![*end];
+    let mut pos = *end;
+    while pos != *start {
+        let (layer, point) = pos;
+        pos = came_from[layer].get(&point).unwrap();
+        path.push(pos);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::parse_ascii;
+
+    use super::*;
+
+    fn building() -> Result<LayeredMap, Box<dyn std::error::Error>> {
+        let ground = parse_ascii("
+            ........
+            ######..
+            ........
+        ")?.map;
+        let first = parse_ascii("
+            ........
+            .######.
+            ........
+        ")?.map;
+        Ok(LayeredMap::new(vec![ground, first]))
+    }
+
+    #[test]
+    fn test_same_layer() -> Result<(), Box<dyn std::error::Error>> {
+        let map = building()?;
+
+        let got = find_path_layered_impl(&map, (0, (0, 0)), (0, (0, 2)))?;
+        assert_eq!(got.first(), Some(&(0, (0, 0))));
+        assert_eq!(got.last(), Some(&(0, (0, 2))));
+        assert!(got.iter().all(|(layer, _)| *layer == 0));
+        assert!(got.len() > 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stairs() -> Result<(), Box<dyn std::error::Error>> {
+        let mut map = building()?;
+        map.add_two_way_portal((0, (0, 0)), (1, (0, 0)), 1.5);
+
+        let got = find_path_layered_impl(&map, (0, (1, 0)), (1, (0, 2)))?;
+        assert_eq!(got, vec![(0, (1, 0)), (0, (0, 0)), (1, (0, 0)), (1, (0, 2))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_one_way_portal() -> Result<(), Box<dyn std::error::Error>> {
+        let mut map = building()?;
+        map.add_portal((1, (7, 0)), (0, (0, 0)), 1.);
+
+        assert!(find_path_layered_impl(&map, (0, (1, 0)), (1, (0, 2))).is_err());
+        let got = find_path_layered_impl(&map, (1, (0, 2)), (0, (3, 0)))?;
+        assert_eq!(&got[got.len() - 3..], &[(1, (7, 0)), (0, (0, 0)), (0, (3, 0))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_portal_shortcut() -> Result<(), Box<dyn std::error::Error>> {
+        let corridor = parse_ascii(&".".repeat(41))?.map;
+        let mut map = LayeredMap::new(vec![corridor, parse_ascii(".")?.map]);
+        // Going back to take the portals is shorter than the straight line to the goal.
+        map.add_portal((0, (10, 0)), (1, (0, 0)), 0.5);
+        map.add_portal((1, (0, 0)), (0, (40, 0)), 0.5);
+
+        let got = find_path_layered_impl(&map, (0, (20, 0)), (0, (40, 0)))?;
+        assert_eq!(got, vec![(0, (20, 0)), (0, (10, 0)), (1, (0, 0)), (0, (40, 0))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping_layer() -> Result<(), Box<dyn std::error::Error>> {
+        let world = GridMap::from_ascii("
+            ..........
+            ..#.......
+            ..#.......
+            ..#.......
+        ")?.with_wrapping(true);
+        let mut map = LayeredMap::new(vec![world, parse_ascii(".")?.map]);
+        // The portals cost less than the straight line inside the map, but more than through the seam.
+        map.add_portal((0, (1, 0)), (1, (0, 0)), 2.5);
+        map.add_portal((1, (0, 0)), (0, (7, 3)), 2.5);
+
+        let got = find_path_layered_impl(&map, (0, (1, 0)), (0, (7, 3)))?;
+        assert_eq!(got, vec![(0, (1, 0)), (0, (7, 3))]);
+        Ok(())
+    }
+}
@@ -1,30 +1,83 @@
-use numpy::PyArray2;
+use std::path::Path;
+
+use ndarray::Array3;
+use numpy::{PyArray2, PyArray3};
 use pyo3::exceptions;
 use pyo3::prelude::*;
+use pyo3::types::PyAny;
 use pyo3::wrap_pyfunction;
 
+pub use crate::ara::{AraSolution, AraStar};
+pub use crate::ascii::{AsciiMap, parse_ascii, render_ascii};
+pub use crate::bidirectional::find_path_bidirectional;
+pub use crate::bit_grid::{BitGrid, find_path_jps};
+pub use crate::chunked::{ChunkedGrid, ChunkedMap, ChunkLoader};
+pub use crate::dstar_lite::DStarLite;
 use crate::exit_red_zone::exit_red_zone_impl;
-pub use crate::find_path::find_path_impl;
-pub use crate::grid::Grid;
-pub use crate::map::{Bounded, GridMap};
-use crate::point::{is_in_bounds, Point2D};
+pub use crate::flow_field::{distance_field_impl, flow_field_impl};
+pub use crate::find_path::{find_path_chunked, find_path_impl, find_path_to_any, find_path_traced, find_path_world_impl, PathResult, SearchTrace};
+pub use crate::frame::{Frame, WorldPoint};
+pub use crate::grid::{CellStorage, Grid};
+pub use crate::grid3d::Grid3D;
+pub use crate::hpa::Hpa;
+pub use crate::hex::{axial_to_offset, hex_distance, hex_line, HexMap, offset_to_axial};
+pub use crate::image::{load_png, render_png, render_svg};
+pub use crate::landmarks::{LandmarkMap, Landmarks};
+pub use crate::layered::{find_path_layered_impl, LayeredMap, LayerPoint};
+pub use crate::lpa::Lpa;
+pub use crate::map::{Bounded, GridMap, Map};
+pub use crate::movingai::{load_movingai_map, load_movingai_scenarios, parse_movingai_map, parse_movingai_scenarios, Scenario};
+use crate::point::{is_in_bounds, is_in_bounds_3d};
+use crate::py_map::PyMap;
+pub use crate::point::{euclidean_distance_3d, path_length, Point2D, Point3D};
+pub use crate::smoothing::{pull_string, smooth_path_impl};
+pub use crate::spline::{curvatures, spline_path_impl, TrajectoryPoint};
+pub use crate::ros_map::{Occupancy, RosMap, RosMapMetadata};
+pub use crate::visibility_graph::{convex_corners, find_path_visibility, VisibilityGraph};
+pub use crate::voxel::{find_path_3d_impl, line_3d, VoxelMap};
 
+mod ara;
+mod ascii;
+mod bidirectional;
+mod bit_grid;
+mod chunked;
+mod dstar_lite;
+mod encoding;
 mod errors;
 mod exit_red_zone;
 mod find_path;
+mod flow_field;
+mod frame;
 mod grid;
+mod grid3d;
 mod heap;
+mod hex;
+mod hpa;
+mod image;
+mod landmarks;
+mod layered;
 mod line_of_sight;
+mod lpa;
 mod map;
+mod movingai;
 mod neighbors;
+mod pgm;
 mod point;
+mod py_map;
+mod ros_map;
+mod smoothing;
+mod spline;
+mod visibility_graph;
+mod voxel;
 
 
-#[pyfunction]
-pub fn find_path(obstacles: &PyArray2<bool>, start: Point2D, end: Point2D) -> PyResult<Vec<Point2D>> {
+/// Searches a path between two cells. With `wrapping`, the map is a torus whose opposite edges
+/// are adjacent.
+#[pyfunction(obstacles, start, end, wrapping = "false")]
+pub fn find_path(obstacles: &PyArray2<bool>, start: Point2D, end: Point2D, wrapping: bool) -> PyResult<Vec<Point2D>> {
     let obstacles = obstacles.to_owned_array();
     let obstacles = Grid::from(obstacles);
-    let map = GridMap::new(obstacles);
+    let map = GridMap::new(obstacles).with_wrapping(wrapping);
 
     if !is_in_bounds(start, map.boundaries()) {
         return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
@@ -46,6 +99,179 @@ pub fn find_path(obstacles: &PyArray2<bool>, start: Point2D, end: Point2D) -> Py
     Ok(result)
 }
 
+/// Searches a path to the nearest of `goals`, returns the goal reached and the path to it.
+#[pyfunction(obstacles, start, goals, wrapping = "false")]
+pub fn find_path_to_nearest(obstacles: &PyArray2<bool>, start: Point2D, goals: Vec<Point2D>, wrapping: bool) -> PyResult<(Point2D, Vec<Point2D>)> {
+    let map = GridMap::new(Grid::from(obstacles.to_owned_array())).with_wrapping(wrapping);
+
+    if !is_in_bounds(start, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if let Some(goal) = goals.iter().find(|goal| !is_in_bounds(**goal, map.boundaries())) {
+        return Err(exceptions::ValueError::py_err(format!("goal position {:?} not in bounds", goal)));
+    }
+
+    match find_path_to_any(&map, start, &goals) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+/// Returns the cost of the shortest path from every cell to `goal`, infinite for obstacles and
+/// cells which cannot reach `goal`.
+#[pyfunction(obstacles, goal, wrapping = "false")]
+pub fn distance_field(py: Python, obstacles: &PyArray2<bool>, goal: Point2D, wrapping: bool) -> PyResult<Py<PyArray2<f64>>> {
+    let map = GridMap::new(Grid::from(obstacles.to_owned_array())).with_wrapping(wrapping);
+
+    if !is_in_bounds(goal, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("goal position not in bounds".to_string()));
+    }
+
+    let distances = distance_field_impl(&map, goal).as_array().to_owned();
+    Ok(PyArray2::from_owned_array(py, distances).to_owned())
+}
+
+/// Returns, for every cell, the offset `(dx, dy)` to the next cell on a shortest path to `goal`,
+/// as an array of shape `(width, height, 2)`. The offset is `(0, 0)` for the goal, obstacles and
+/// cells which cannot reach `goal`.
+#[pyfunction(obstacles, goal, wrapping = "false")]
+pub fn flow_field(py: Python, obstacles: &PyArray2<bool>, goal: Point2D, wrapping: bool) -> PyResult<Py<PyArray3<i64>>> {
+    let map = GridMap::new(Grid::from(obstacles.to_owned_array())).with_wrapping(wrapping);
+
+    if !is_in_bounds(goal, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("goal position not in bounds".to_string()));
+    }
+
+    let directions = flow_field_impl(&map, &distance_field_impl(&map, goal));
+    let (width, height) = directions.as_array().dim();
+    let directions = Array3::from_shape_fn((width, height, 2), |(x, y, i)| {
+        let (dx, dy) = directions.as_array()[(x, y)];
+        if i == 0 { dx as i64 } else { dy as i64 }
+    });
+    Ok(PyArray3::from_owned_array(py, directions).to_owned())
+}
+
+#[pyfunction(obstacles, start, end, resolution, origin, rotation = "0.0")]
+pub fn find_path_world(obstacles: &PyArray2<bool>, start: WorldPoint, end: WorldPoint, resolution: f64, origin: WorldPoint, rotation: f64) -> PyResult<Vec<WorldPoint>> {
+    let obstacles = obstacles.to_owned_array();
+    let obstacles = Grid::from(obstacles);
+    let frame = Frame::new(origin, resolution).with_rotation(rotation);
+    let map = GridMap::with_frame(obstacles, frame);
+
+    if !is_in_bounds(frame.world_to_cell(&start), map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !is_in_bounds(frame.world_to_cell(&end), map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    match find_path_world_impl(&map, start, end) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+/// Searches a path in a map given by python code instead of an array, see `PyMap`.
+///
+/// Scores are only stored for the explored part of `boundaries`, which can be much larger than
+/// the area actually searched.
+#[pyfunction]
+pub fn find_path_callback(map: &PyAny, start: Point2D, end: Point2D, boundaries: (Point2D, Point2D)) -> PyResult<Vec<Point2D>> {
+    let map = PyMap::new(map, boundaries)?;
+
+    if !is_in_bounds(start, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !is_in_bounds(end, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    let result = find_path_chunked(&map, start, end);
+    if let Some(e) = map.take_error() {
+        return Err(e);
+    }
+    match result {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+/// Searches a path in a map of pointy-top hexagons, `obstacles` being indexed by the "odd-r"
+/// offset coordinates `(column, row)` of the hexagons.
+#[pyfunction]
+pub fn find_path_hex(obstacles: &PyArray2<bool>, start: Point2D, end: Point2D) -> PyResult<Vec<Point2D>> {
+    let obstacles = obstacles.to_owned_array();
+    let map = HexMap::new(Grid::from(obstacles));
+
+    if !is_in_bounds(start, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !is_in_bounds(end, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    match find_path_impl(&map, start, end) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+#[pyfunction]
+pub fn find_path_3d(obstacles: &PyArray3<bool>, start: Point3D, end: Point3D) -> PyResult<Vec<Point3D>> {
+    let obstacles = obstacles.to_owned_array();
+    let map = VoxelMap::new(Grid3D::from(obstacles));
+
+    if !is_in_bounds_3d(start, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !is_in_bounds_3d(end, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    match find_path_3d_impl(&map, start, end) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+/// Searches a path across several layers, e.g. the floors of a building. `portals` are two-way
+/// connections `(a, b, cost)` between cells `(layer, (x, y))` of any layers.
+#[pyfunction]
+pub fn find_path_layered(layers: Vec<&PyArray2<bool>>, portals: Vec<(LayerPoint, LayerPoint, f64)>, start: LayerPoint, end: LayerPoint) -> PyResult<Vec<LayerPoint>> {
+    let layers = layers.iter()
+        .map(|obstacles| GridMap::new(Grid::from(obstacles.to_owned_array())))
+        .collect();
+    let mut map = LayeredMap::new(layers);
+    let in_bounds = |(layer, point): LayerPoint| map.layers().get(layer).is_some_and(|l| is_in_bounds(point, l.boundaries()));
+
+    for (a, b, _) in portals.iter() {
+        if !in_bounds(*a) || !in_bounds(*b) {
+            return Err(exceptions::ValueError::py_err("portal not in bounds".to_string()));
+        }
+    }
+
+    if !in_bounds(start) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !in_bounds(end) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    for (a, b, cost) in portals {
+        map.add_two_way_portal(a, b, cost);
+    }
+    match find_path_layered_impl(&map, start, end) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
 #[pyfunction]
 pub fn exit_red_zone(obstacles: &PyArray2<bool>, start: Point2D) -> PyResult<Point2D> {
     let obstacles = obstacles.to_owned_array();
@@ -62,11 +288,143 @@ pub fn exit_red_zone(obstacles: &PyArray2<bool>, start: Point2D) -> PyResult<Poi
     Ok(result)
 }
 
+/// Removes the redundant waypoints of a path, and with `string_pulling` also lets waypoints move
+/// along the path to turn next to the corners of the obstacles (see `pull_string`).
+#[pyfunction(obstacles, path, string_pulling = "false")]
+pub fn smooth_path(obstacles: &PyArray2<bool>, path: Vec<Point2D>, string_pulling: bool) -> PyResult<Vec<Point2D>> {
+    let map = GridMap::new(Grid::from(obstacles.to_owned_array()));
+
+    if let Some(waypoint) = path.iter().find(|waypoint| !is_in_bounds(**waypoint, map.boundaries())) {
+        return Err(exceptions::ValueError::py_err(format!("waypoint {:?} not in bounds", waypoint)));
+    }
+
+    match string_pulling {
+        true => Ok(pull_string(&map, &path)),
+        false => Ok(smooth_path_impl(&map, &path)),
+    }
+}
+
+/// Turns the waypoints of a path into a smooth trajectory sampled about every `step` cells, raises
+/// `ValueError` when the straight line between two waypoints goes through obstacles.
+#[pyfunction(obstacles, path, step = "0.25")]
+pub fn spline_path(obstacles: &PyArray2<bool>, path: Vec<Point2D>, step: f64) -> PyResult<Vec<TrajectoryPoint>> {
+    let map = GridMap::new(Grid::from(obstacles.to_owned_array()));
+
+    if let Some(waypoint) = path.iter().find(|waypoint| !is_in_bounds(**waypoint, map.boundaries())) {
+        return Err(exceptions::ValueError::py_err(format!("waypoint {:?} not in bounds", waypoint)));
+    }
+
+    match spline_path_impl(&map, &path, step) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(exceptions::ValueError::py_err(e.to_string())),
+    }
+}
+
+#[pyfunction(obstacles, path, start = "None", goal = "None")]
+pub fn render_map(obstacles: &PyArray2<bool>, path: Vec<Point2D>, start: Option<Point2D>, goal: Option<Point2D>) -> String {
+    let obstacles = obstacles.to_owned_array();
+    let map = GridMap::new(Grid::from(obstacles));
+    render_ascii(&map, start, goal, &path)
+}
+
+#[pyfunction(filename, threshold = "128")]
+pub fn load_png_map(py: Python, filename: &str, threshold: u8) -> PyResult<Py<PyArray2<bool>>> {
+    let map = match load_png(filename, threshold) {
+        Ok(m) => m,
+        Err(e) => return Err(exceptions::RuntimeError::py_err(e.to_string())),
+    };
+    let obstacles = map.obstacles().as_array().to_owned();
+    Ok(PyArray2::from_owned_array(py, obstacles).to_owned())
+}
+
+/// Searches a path and draws the map, the explored cells and the path in a PNG or SVG file.
+#[pyfunction(filename, obstacles, start, end, cell_size = "4")]
+pub fn save_search_image(filename: &str, obstacles: &PyArray2<bool>, start: Point2D, end: Point2D, cell_size: usize) -> PyResult<()> {
+    let obstacles = obstacles.to_owned_array();
+    let map = GridMap::new(Grid::from(obstacles));
+
+    if !is_in_bounds(start, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("start position not in bounds".to_string()));
+    }
+
+    if !is_in_bounds(end, map.boundaries()) {
+        return Err(exceptions::ValueError::py_err("end position not in bounds".to_string()));
+    }
+
+    let trace = find_path_traced(&map, start, end);
+    let path = trace.path.unwrap_or_default();
+    let image = if filename.ends_with(".svg") {
+        Ok(render_svg(&map, Some(&trace.explored), &path, cell_size).into_bytes())
+    } else {
+        render_png(&map, Some(&trace.explored), &path, cell_size)
+    };
+
+    match image.and_then(|image| Ok(std::fs::write(filename, image)?)) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
+/// A ROS map as returned to python: the grid, its resolution and its origin `(x, y, yaw)`.
+type PyRosMap<T> = (Py<PyArray2<T>>, f64, (f64, f64, f64));
+
+#[pyfunction(yaml_path, unknown_is_obstacle = "true")]
+pub fn load_ros_map(py: Python, yaml_path: &str, unknown_is_obstacle: bool) -> PyResult<PyRosMap<bool>> {
+    let map = match RosMap::load(yaml_path) {
+        Ok(m) => m,
+        Err(e) => return Err(exceptions::RuntimeError::py_err(e.to_string())),
+    };
+    let obstacles = map.to_grid_map(unknown_is_obstacle).obstacles().as_array().to_owned();
+    let obstacles = PyArray2::from_owned_array(py, obstacles).to_owned();
+    Ok((obstacles, map.metadata.resolution, map.metadata.origin))
+}
+
+#[pyfunction]
+pub fn load_ros_cost_map(py: Python, yaml_path: &str, unknown_cost: f64) -> PyResult<PyRosMap<f64>> {
+    let map = match RosMap::load(yaml_path) {
+        Ok(m) => m,
+        Err(e) => return Err(exceptions::RuntimeError::py_err(e.to_string())),
+    };
+    let costs = map.cost_grid(unknown_cost).as_array().to_owned();
+    let costs = PyArray2::from_owned_array(py, costs).to_owned();
+    Ok((costs, map.metadata.resolution, map.metadata.origin))
+}
+
+#[pyfunction]
+pub fn save_ros_map(yaml_path: &str, obstacles: &PyArray2<bool>, resolution: f64, origin: (f64, f64, f64)) -> PyResult<()> {
+    let (x, y, yaw) = origin;
+    let frame = Frame::new((x, y), resolution).with_rotation(yaw);
+    let map = GridMap::with_frame(Grid::from(obstacles.to_owned_array()), frame);
+    let image = Path::new(yaml_path).with_extension("pgm");
+    let image = image.file_name().unwrap_or_default();
+
+    match RosMap::from_grid_map(&map, image).save(yaml_path) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(exceptions::RuntimeError::py_err(e.to_string())),
+    }
+}
+
 /// This module is a python module implemented in Rust.
 #[pymodule]
 fn grid_pathfinding(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_wrapped(wrap_pyfunction!(exit_red_zone))?;
     m.add_wrapped(wrap_pyfunction!(find_path))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_world))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_to_nearest))?;
+    m.add_wrapped(wrap_pyfunction!(distance_field))?;
+    m.add_wrapped(wrap_pyfunction!(flow_field))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_callback))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_hex))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_3d))?;
+    m.add_wrapped(wrap_pyfunction!(find_path_layered))?;
+    m.add_wrapped(wrap_pyfunction!(smooth_path))?;
+    m.add_wrapped(wrap_pyfunction!(spline_path))?;
+    m.add_wrapped(wrap_pyfunction!(render_map))?;
+    m.add_wrapped(wrap_pyfunction!(load_png_map))?;
+    m.add_wrapped(wrap_pyfunction!(save_search_image))?;
+    m.add_wrapped(wrap_pyfunction!(load_ros_map))?;
+    m.add_wrapped(wrap_pyfunction!(load_ros_cost_map))?;
+    m.add_wrapped(wrap_pyfunction!(save_ros_map))?;
 
     Ok(())
 }
@@ -12,6 +12,13 @@ pub fn line_of_sight(start: &Point2D, end: &Point2D, obstacles: &Grid<bool>) ->
         .any(|has_obstacle| has_obstacle)
 }
 
+/// Same as `line_of_sight` for maps whose obstacles are not stored in a `Grid`.
+pub(crate) fn line_of_sight_with(start: &Point2D, end: &Point2D, obstacle: impl Fn(&Point2D) -> bool) -> bool {
+    !Bresenham::new(*start, *end)
+        .chain(once(*end))
+        .any(|pos| obstacle(&pos))
+}
+
 #[cfg(test)]
 mod tests {
     use ndarray::Array2;
@@ -0,0 +1,172 @@
+use crate::grid::Grid;
+use crate::heap::DecreaseKeyHeap;
+use crate::map::{descend, Map, step_cost};
+use crate::point::Point2D;
+
+/// A Lifelong Planning A* planner for a fixed goal, answering path queries from any start and
+/// repairing its costs when the map changes.
+///
+/// The search goes backward from the goal without heuristic, since starts are not known in
+/// advance: each query only expands the cells closer to the goal than its start which were not
+/// expanded yet, and map updates only invalidate the costs depending on the changed cells.
+pub struct Lpa {
+    goal: Point2D,
+    g: Grid<f64>,
+    rhs: Grid<f64>,
+    open_set: DecreaseKeyHeap<Point2D>,
+}
+
+impl Lpa {
+    pub fn new(map: &impl Map, goal: Point2D) -> Lpa {
+        let boundaries = map.boundaries();
+        let mut planner = Lpa {
+            goal,
+            g: Grid::new(boundaries, f64::INFINITY),
+            rhs: Grid::new(boundaries, f64::INFINITY),
+            open_set: DecreaseKeyHeap::new(),
+        };
+        planner.rhs.set(&goal, 0.);
+        planner.open_set.push(goal, 0.);
+        planner
+    }
+
+    pub fn goal(&self) -> Point2D {
+        self.goal
+    }
+
+    /// Returns the cost of the shortest path from `start` to the goal.
+    pub fn cost_to_goal(&mut self, map: &impl Map, start: &Point2D) -> f64 {
+        self.compute_shortest_path(map, start);
+        self.g.get(start)
+    }
+
+    /// Returns the shortest path from `start` to the goal, from cell to adjacent cell.
+    pub fn find_path(&mut self, map: &impl Map, start: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        self.compute_shortest_path(map, &start);
+        descend(map, &self.g, start, self.goal)
+    }
+
+    /// Takes into account the cells whose obstacle status changed in `map`. Costs are repaired by
+    /// the next queries.
+    pub fn update(&mut self, map: &impl Map, changed: &[Point2D]) {
+        for cell in changed.iter() {
+            self.update_vertex(map, cell);
+            for neighbor in map.neighbors(cell) {
+                self.update_vertex(map, &neighbor);
+            }
+        }
+    }
+
+    fn key(&self, cell: &Point2D) -> f64 {
+        self.g.get(cell).min(self.rhs.get(cell))
+    }
+
+    fn update_vertex(&mut self, map: &impl Map, cell: &Point2D) {
+        if *cell != self.goal {
+            let rhs = map.neighbors(cell)
+                .map(|n| step_cost(map, cell, &n) + self.g.get(&n))
+                .fold(f64::INFINITY, f64::min);
+            self.rhs.set(cell, rhs);
+        }
+        if self.g.get(cell) != self.rhs.get(cell) {
+            self.open_set.push(*cell, self.key(cell));
+        } else {
+            self.open_set.remove(cell);
+        }
+    }
+
+    fn compute_shortest_path(&mut self, map: &impl Map, start: &Point2D) {
+        while let Some(top) = self.open_set.peek() {
+            if top.f_score >= self.key(start) && self.g.get(start) == self.rhs.get(start) {
+                break;
+            }
+
+            let cell = self.open_set.pop().unwrap().position;
+            if self.g.get(&cell) > self.rhs.get(&cell) {
+                self.g.set(&cell, self.rhs.get(&cell));
+            } else {
+                self.g.set(&cell, f64::INFINITY);
+                self.update_vertex(map, &cell);
+            }
+            for neighbor in map.neighbors(&cell) {
+                self.update_vertex(map, &neighbor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::parse_ascii;
+    use crate::dstar_lite::DStarLite;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    fn map_with(obstacles: &[Point2D]) -> Result<GridMap, Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ..........
+            ..........
+            ....##....
+            ....##....
+            ..........
+        ")?.map;
+        let mut grid = map.obstacles().clone();
+        for cell in obstacles.iter() {
+            grid.set(cell, true);
+        }
+        Ok(GridMap::new(grid))
+    }
+
+    fn assert_optimal(planner: &mut Lpa, map: &GridMap, start: Point2D) -> Result<(), Box<dyn std::error::Error>> {
+        let path = planner.find_path(map, start)?;
+        let want = DStarLite::new(map, start, planner.goal()).plan(map)?;
+        let length = |path: &[Point2D]| path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum::<f64>();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&planner.goal()));
+        assert!((length(&path) - length(&want)).abs() < 1e-9);
+        assert!((planner.cost_to_goal(map, &start) - length(&want)).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_queries_from_several_starts() -> Result<(), Box<dyn std::error::Error>> {
+        let map = map_with(&[])?;
+        let mut planner = Lpa::new(&map, (9, 0));
+
+        for start in [(0, 4), (0, 0), (5, 4), (9, 1)].iter() {
+            assert_optimal(&mut planner, &map, *start)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_updates() -> Result<(), Box<dyn std::error::Error>> {
+        let map = map_with(&[])?;
+        let mut planner = Lpa::new(&map, (9, 0));
+        assert_optimal(&mut planner, &map, (0, 4))?;
+
+        let changed = [(6, 0), (6, 1), (6, 2), (6, 3)];
+        let map = map_with(&changed)?;
+        planner.update(&map, &changed);
+        assert_optimal(&mut planner, &map, (0, 4))?;
+        assert_optimal(&mut planner, &map, (0, 0))?;
+
+        let map = map_with(&changed[1..])?;
+        planner.update(&map, &changed[..1]);
+        assert_optimal(&mut planner, &map, (0, 4))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable_start() -> Result<(), Box<dyn std::error::Error>> {
+        let changed = [(8, 0), (8, 1), (9, 1)];
+        let map = map_with(&changed)?;
+        let mut planner = Lpa::new(&map, (9, 0));
+
+        assert!(planner.find_path(&map, (0, 0)).is_err());
+        assert_eq!(planner.cost_to_goal(&map, &(0, 0)), f64::INFINITY);
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+use std::time::Instant;
+
+use grid_pathfinding::{AraStar, BitGrid, Bounded, DStarLite, find_path_bidirectional, find_path_impl, find_path_jps, find_path_visibility, GridMap, Hpa, load_movingai_map, load_movingai_scenarios, load_png, Lpa, Map, parse_ascii, Point2D, RosMap};
+
+const USAGE: &str = "\
+Usage: grid-pathfinding [OPTIONS] <MAP>
+
+Loads a map, searches a path between two cells and prints it with statistics.
+
+Options:
+    --format <FORMAT>        Map format: ascii, png, movingai or ros (guessed from the extension by default)
+    --start <X,Y>            Start cell (defaults to the S marker of ascii maps)
+    --goal <X,Y>             Goal cell (defaults to the G marker of ascii maps)
+    --scenario <FILE>        Runs every query of a MovingAI .scen file instead of --start/--goal
+    --algorithm <ALGORITHM>  Search algorithm: any-angle (default), bidirectional, visibility-graph,
+                             dstar-lite, lpa, ara (last solution of ARA*), hpa (clusters of 16 cells)
+                             or jps (not on wrapping maps)
+    --output <OUTPUT>        Output format: json (default) or csv
+    --threshold <VALUE>      Gray level under which png pixels are obstacles (default: 128)
+    --unknown-is-free        Treats unknown cells of ros maps as free instead of obstacles
+    --wrapping               Wraps the map around its edges, like a torus
+    -h, --help               Prints this message
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    Png,
+    MovingAi,
+    Ros,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    AnyAngle,
+    Bidirectional,
+    VisibilityGraph,
+    DStarLite,
+    Lpa,
+    Ara,
+    Hpa,
+    Jps,
+}
+
+/// Size of the clusters of HPA*.
+const HPA_CLUSTER_SIZE: usize = 16;
+
+/// A search algorithm along with the structures it builds once for all the queries of a map.
+struct Planner {
+    algorithm: Algorithm,
+    hpa: Option<Hpa>,
+    bit_grid: Option<BitGrid>,
+}
+
+impl Planner {
+    fn new(map: &GridMap, algorithm: Algorithm) -> Result<Planner, Box<dyn std::error::Error>> {
+        if algorithm == Algorithm::Jps && map.wrapping() {
+            return Err("jps does not support wrapping maps".into());
+        }
+        Ok(Planner {
+            algorithm,
+            hpa: if algorithm == Algorithm::Hpa { Some(Hpa::new(map, HPA_CLUSTER_SIZE)) } else { None },
+            bit_grid: if algorithm == Algorithm::Jps { Some(BitGrid::from(map.obstacles())) } else { None },
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+    Json,
+    Csv,
+}
+
+struct Options {
+    map: String,
+    format: Option<Format>,
+    start: Option<Point2D>,
+    goal: Option<Point2D>,
+    scenario: Option<String>,
+    algorithm: Algorithm,
+    output: Output,
+    threshold: u8,
+    unknown_is_free: bool,
+    wrapping: bool,
+}
+
+struct Query {
+    start: Point2D,
+    goal: Point2D,
+    optimal_length: Option<f64>,
+}
+
+struct QueryResult {
+    query: Query,
+    path: Option<Vec<Point2D>>,
+    length: Option<f64>,
+    time_us: u128,
+}
+
+fn parse_point(value: &str) -> Result<Point2D, String> {
+    let mut coordinates = value.split(',').map(|v| v.trim().parse::<isize>());
+    match (coordinates.next(), coordinates.next(), coordinates.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+        _ => Err(format!("invalid cell {:?}, expected X,Y", value)),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> Result<Options, String> {
+    let mut options = Options {
+        map: String::new(),
+        format: None,
+        start: None,
+        goal: None,
+        scenario: None,
+        algorithm: Algorithm::AnyAngle,
+        output: Output::Json,
+        threshold: 128,
+        unknown_is_free: false,
+        wrapping: false,
+    };
+    let mut map = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                exit(0);
+            }
+            "--format" => options.format = Some(match value()?.as_str() {
+                "ascii" => Format::Ascii,
+                "png" => Format::Png,
+                "movingai" => Format::MovingAi,
+                "ros" => Format::Ros,
+                v => return Err(format!("unknown map format {:?}", v)),
+            }),
+            "--start" => options.start = Some(parse_point(&value()?)?),
+            "--goal" => options.goal = Some(parse_point(&value()?)?),
+            "--scenario" => options.scenario = Some(value()?),
+            "--algorithm" => options.algorithm = match value()?.as_str() {
+                "any-angle" => Algorithm::AnyAngle,
+                "bidirectional" => Algorithm::Bidirectional,
+                "visibility-graph" => Algorithm::VisibilityGraph,
+                "dstar-lite" => Algorithm::DStarLite,
+                "lpa" => Algorithm::Lpa,
+                "ara" => Algorithm::Ara,
+                "hpa" => Algorithm::Hpa,
+                "jps" => Algorithm::Jps,
+                v => return Err(format!("unknown algorithm {:?}", v)),
+            },
+            "--output" => options.output = match value()?.as_str() {
+                "json" => Output::Json,
+                "csv" => Output::Csv,
+                v => return Err(format!("unknown output format {:?}", v)),
+            },
+            "--threshold" => options.threshold = value()?.parse().map_err(|_| "invalid threshold".to_string())?,
+            "--unknown-is-free" => options.unknown_is_free = true,
+            "--wrapping" => options.wrapping = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if map.is_none() => map = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    options.map = map.ok_or_else(|| "missing map".to_string())?;
+    Ok(options)
+}
+
+fn guess_format(path: &str) -> Result<Format, String> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("png") => Ok(Format::Png),
+        Some("map") => Ok(Format::MovingAi),
+        Some("yaml") | Some("yml") => Ok(Format::Ros),
+        Some("txt") | Some("ascii") => Ok(Format::Ascii),
+        _ => Err(format!("cannot guess the format of {:?}, use --format", path)),
+    }
+}
+
+/// A map along with its start and goal markers, only ascii maps have markers.
+type LoadedMap = (GridMap, Option<Point2D>, Option<Point2D>);
+
+fn load_map(options: &Options) -> Result<LoadedMap, Box<dyn std::error::Error>> {
+    let format = match options.format {
+        Some(format) => format,
+        None => guess_format(&options.map)?,
+    };
+    let (map, start, goal) = match format {
+        Format::Ascii => {
+            let ascii = parse_ascii(&fs::read_to_string(&options.map)?)?;
+            (ascii.map, ascii.start, ascii.goal)
+        }
+        Format::Png => (load_png(&options.map, options.threshold)?, None, None),
+        Format::MovingAi => (load_movingai_map(&options.map)?, None, None),
+        Format::Ros => (RosMap::load(&options.map)?.to_grid_map(!options.unknown_is_free), None, None),
+    };
+    Ok((map.with_wrapping(options.wrapping), start, goal))
+}
+
+fn queries(options: &Options, start: Option<Point2D>, goal: Option<Point2D>) -> Result<Vec<Query>, Box<dyn std::error::Error>> {
+    if let Some(scenario) = &options.scenario {
+        return Ok(load_movingai_scenarios(scenario)?
+            .into_iter()
+            .map(|s| Query { start: s.start, goal: s.goal, optimal_length: Some(s.optimal_length) })
+            .collect());
+    }
+
+    let start = options.start.or(start).ok_or("missing --start")?;
+    let goal = options.goal.or(goal).ok_or("missing --goal")?;
+    Ok(vec![Query { start, goal, optimal_length: None }])
+}
+
+fn run(map: &GridMap, planner: &Planner, query: Query) -> Result<QueryResult, Box<dyn std::error::Error>> {
+    let ((min_x, min_y), (max_x, max_y)) = map.boundaries();
+    for (x, y) in [query.start, query.goal].iter() {
+        if *x < min_x || *y < min_y || *x >= max_x || *y >= max_y {
+            return Err(format!("cell {:?} is not in the map", (x, y)).into());
+        }
+    }
+
+    let now = Instant::now();
+    let path = match planner.algorithm {
+        Algorithm::AnyAngle => find_path_impl(map, query.start, query.goal).ok(),
+        Algorithm::Bidirectional => find_path_bidirectional(map, query.start, query.goal).ok(),
+        Algorithm::VisibilityGraph => find_path_visibility(map, query.start, query.goal).ok(),
+        Algorithm::DStarLite => DStarLite::new(map, query.start, query.goal).plan(map).ok(),
+        Algorithm::Lpa => Lpa::new(map, query.goal).find_path(map, query.start).ok(),
+        Algorithm::Ara => AraStar::new(map, query.start, query.goal).last().map(|solution| solution.path),
+        Algorithm::Hpa => planner.hpa.as_ref().and_then(|hpa| hpa.find_path(map, query.start, query.goal).ok()),
+        Algorithm::Jps => planner.bit_grid.as_ref().and_then(|grid| find_path_jps(grid, query.start, query.goal).ok()),
+    };
+    let time_us = now.elapsed().as_micros();
+    // Waypoints of wrapping maps may be on both sides of the map, so `path_length` does not apply.
+    let length = path.as_ref().map(|path| path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum());
+    Ok(QueryResult { query, path, length, time_us })
+}
+
+fn to_json(results: &[QueryResult]) -> String {
+    let mut json = String::from("[\n");
+    for (i, result) in results.iter().enumerate() {
+        let Query { start: (sx, sy), goal: (gx, gy), optimal_length } = result.query;
+        write!(json, "  {{\"start\": [{}, {}], \"goal\": [{}, {}], ", sx, sy, gx, gy).unwrap();
+        match (&result.path, result.length) {
+            (Some(path), Some(length)) => {
+                let points: Vec<String> = path.iter().map(|(x, y)| format!("[{}, {}]", x, y)).collect();
+                write!(json, "\"found\": true, \"length\": {}, ", length).unwrap();
+                write!(json, "\"path\": [{}], ", points.join(", ")).unwrap();
+            }
+            _ => json.push_str("\"found\": false, \"length\": null, \"path\": null, "),
+        }
+        if let Some(optimal_length) = optimal_length {
+            write!(json, "\"optimal_length\": {}, ", optimal_length).unwrap();
+        }
+        write!(json, "\"time_us\": {}}}", result.time_us).unwrap();
+        json.push_str(if i + 1 < results.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("]\n");
+    json
+}
+
+fn to_csv(results: &[QueryResult]) -> String {
+    let mut csv = String::from("start_x,start_y,goal_x,goal_y,found,length,optimal_length,time_us,path\n");
+    for result in results.iter() {
+        let Query { start: (sx, sy), goal: (gx, gy), optimal_length } = result.query;
+        let (length, path) = match (&result.path, result.length) {
+            (Some(path), Some(length)) => {
+                let points: Vec<String> = path.iter().map(|(x, y)| format!("{}:{}", x, y)).collect();
+                (length.to_string(), points.join(" "))
+            }
+            _ => (String::new(), String::new()),
+        };
+        let optimal_length = optimal_length.map(|l| l.to_string()).unwrap_or_default();
+        writeln!(
+            csv, "{},{},{},{},{},{},{},{},{}",
+            sx, sy, gx, gy, result.path.is_some(), length, optimal_length, result.time_us, path,
+        ).unwrap();
+    }
+    csv
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprint!("error: {}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    let result = load_map(&options).and_then(|(map, start, goal)| {
+        let planner = Planner::new(&map, options.algorithm)?;
+        queries(&options, start, goal)?
+            .into_iter()
+            .map(|query| run(&map, &planner, query))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    let results = match result {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            exit(1);
+        }
+    };
+
+    match options.output {
+        Output::Json => print!("{}", to_json(&results)),
+        Output::Csv => print!("{}", to_csv(&results)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item=String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    fn results() -> Vec<QueryResult> {
+        vec![
+            QueryResult {
+                query: Query { start: (0, 0), goal: (3, 4), optimal_length: Some(5.) },
+                path: Some(vec![(0, 0), (3, 4)]),
+                length: Some(5.),
+                time_us: 12,
+            },
+            QueryResult {
+                query: Query { start: (1, 2), goal: (-1, 0), optimal_length: None },
+                path: None,
+                length: None,
+                time_us: 3,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse_point() {
+        assert_eq!(parse_point("3,4"), Ok((3, 4)));
+        assert_eq!(parse_point(" -1, 2 "), Ok((-1, 2)));
+        assert!(parse_point("3").is_err());
+        assert!(parse_point("3,4,5").is_err());
+        assert!(parse_point("a,4").is_err());
+        assert!(parse_point("").is_err());
+    }
+
+    #[test]
+    fn test_parse_args() -> Result<(), String> {
+        let options = parse_args(args(&["map.png"]))?;
+        assert_eq!(options.map, "map.png");
+        assert_eq!(options.format, None);
+        assert_eq!(options.algorithm, Algorithm::AnyAngle);
+        assert_eq!(options.output, Output::Json);
+        assert_eq!(options.threshold, 128);
+        assert!(!options.unknown_is_free && !options.wrapping);
+
+        let options = parse_args(args(&[
+            "--format", "ros", "--start", "1,2", "--goal", "3,4", "--algorithm", "dstar-lite", "--output", "csv",
+            "--threshold", "50", "--unknown-is-free", "--wrapping", "map.yaml",
+        ]))?;
+        assert_eq!(options.map, "map.yaml");
+        assert_eq!(options.format, Some(Format::Ros));
+        assert_eq!((options.start, options.goal), (Some((1, 2)), Some((3, 4))));
+        assert_eq!(options.algorithm, Algorithm::DStarLite);
+        assert_eq!(options.output, Output::Csv);
+        assert_eq!(options.threshold, 50);
+        assert!(options.unknown_is_free && options.wrapping);
+
+        let algorithms = [
+            ("bidirectional", Algorithm::Bidirectional), ("visibility-graph", Algorithm::VisibilityGraph), ("lpa", Algorithm::Lpa),
+            ("ara", Algorithm::Ara), ("hpa", Algorithm::Hpa), ("jps", Algorithm::Jps),
+        ];
+        for (name, algorithm) in algorithms {
+            assert_eq!(parse_args(args(&["--algorithm", name, "map.png"]))?.algorithm, algorithm);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        let error = |arguments: &[&str]| parse_args(args(arguments)).err();
+        assert_eq!(error(&["--verbose", "map.png"]), Some("unknown option --verbose".to_string()));
+        assert_eq!(error(&["map.png", "--start"]), Some("missing value for --start".to_string()));
+        assert_eq!(error(&["--start", "1;2", "map.png"]), Some("invalid cell \"1;2\", expected X,Y".to_string()));
+        assert_eq!(error(&["--algorithm", "dijkstra", "map.png"]), Some("unknown algorithm \"dijkstra\"".to_string()));
+        assert_eq!(error(&["--format", "bmp", "map.png"]), Some("unknown map format \"bmp\"".to_string()));
+        assert_eq!(error(&["--output", "xml", "map.png"]), Some("unknown output format \"xml\"".to_string()));
+        assert_eq!(error(&["--threshold", "300", "map.png"]), Some("invalid threshold".to_string()));
+        assert_eq!(error(&["a.png", "b.png"]), Some("unexpected argument b.png".to_string()));
+        assert_eq!(error(&[]), Some("missing map".to_string()));
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(to_json(&results()), "[
+  {\"start\": [0, 0], \"goal\": [3, 4], \"found\": true, \"length\": 5, \"path\": [[0, 0], [3, 4]], \"optimal_length\": 5, \"time_us\": 12},
+  {\"start\": [1, 2], \"goal\": [-1, 0], \"found\": false, \"length\": null, \"path\": null, \"time_us\": 3}
+]
+");
+        assert_eq!(to_json(&[]), "[\n]\n");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(to_csv(&results()), "\
+start_x,start_y,goal_x,goal_y,found,length,optimal_length,time_us,path
+0,0,3,4,true,5,5,12,0:0 3:4
+1,2,-1,0,false,,,3,
+");
+    }
+
+    #[test]
+    fn test_run() -> Result<(), Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            ..#.
+            ..#.
+            ....
+        ")?;
+        let algorithms = [
+            Algorithm::AnyAngle, Algorithm::Bidirectional, Algorithm::VisibilityGraph, Algorithm::DStarLite, Algorithm::Lpa,
+            Algorithm::Ara, Algorithm::Hpa, Algorithm::Jps,
+        ];
+        for algorithm in algorithms {
+            let result = run(&map, &Planner::new(&map, algorithm)?, Query { start: (0, 2), goal: (3, 2), optimal_length: None })?;
+            assert_eq!(result.path.as_ref().and_then(|path| path.last()), Some(&(3, 2)));
+        }
+        let planner = Planner::new(&map, Algorithm::AnyAngle)?;
+        assert!(run(&map, &planner, Query { start: (0, 0), goal: (4, 0), optimal_length: None }).is_err());
+        assert!(Planner::new(&map.with_wrapping(true), Algorithm::Jps).is_err());
+        Ok(())
+    }
+}
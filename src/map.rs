@@ -1,6 +1,14 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+use crate::encoding::CompactGridMap;
+use crate::errors::NoPathFoundError;
+use crate::frame::Frame;
 use crate::grid::Grid;
-use crate::point::Point2D;
-use crate::line_of_sight::line_of_sight;
+use crate::line_of_sight::{line_of_sight, line_of_sight_with};
+use crate::neighbors::get_neighbors;
+use crate::point::{euclidean_distance, Point2D};
 
 pub trait Bounded {
     fn boundaries(&self) -> (Point2D, Point2D);
@@ -9,15 +17,123 @@ pub trait Bounded {
 pub trait Map: Bounded {
     fn obstacle(&self, point: &Point2D) -> bool;
     fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool;
+
+    /// Returns the cells adjacent to `point`, the 8 surrounding cells of a square grid by default.
+    fn neighbors(&self, point: &Point2D) -> impl Iterator<Item=Point2D> {
+        get_neighbors(*point, self.boundaries())
+    }
+
+    /// Returns the length of the straight line between two cells.
+    fn distance(&self, a: &Point2D, b: &Point2D) -> f64 {
+        euclidean_distance(a, b)
+    }
+
+    /// Returns the estimate of the cost of a path between two cells which guides the searches
+    /// from cell to adjacent cell, `distance` by default. Any-angle searches are guided by
+    /// `distance`, since their paths may be shorter than such an estimate.
+    fn heuristic(&self, a: &Point2D, b: &Point2D) -> f64 {
+        self.distance(a, b)
+    }
 }
 
+/// Returns the cost of moving between two adjacent cells, infinite if one of them is an obstacle.
+pub(crate) fn step_cost(map: &impl Map, a: &Point2D, b: &Point2D) -> f64 {
+    if map.obstacle(a) || map.obstacle(b) {
+        f64::INFINITY
+    } else {
+        map.distance(a, b)
+    }
+}
+
+/// Builds a path from `start` to `goal` by following the decreasing costs to `goal`, from cell to
+/// adjacent cell.
+pub(crate) fn descend(map: &impl Map, cost_to_goal: &Grid<f64>, start: Point2D, goal: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    if !cost_to_goal.get(&start).is_finite() {
+        return Err(Box::new(NoPathFoundError()));
+    }
+    let mut path = vec![start];
+    let mut cell = start;
+    while cell != goal {
+        cell = map.neighbors(&cell)
+            .map(|n| (n, step_cost(map, &cell, &n) + cost_to_goal.get(&n)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(n, _)| n)
+            .ok_or(NoPathFoundError())?;
+        path.push(cell);
+    }
+    Ok(path)
+}
+
+/// Boundaries large enough to never clip the neighbors of a cell, which are wrapped instead.
+const NO_BOUNDARIES: (Point2D, Point2D) = ((isize::MIN / 2, isize::MIN / 2), (isize::MAX / 2, isize::MAX / 2));
+
+/// Returns the shortest signed offset from `a` to `b` along an axis which wraps every `size` cells.
+fn wrapped_delta(a: isize, b: isize, size: isize) -> isize {
+    let delta = (b - a).rem_euclid(size);
+    if delta > size / 2 { delta - size } else { delta }
+}
+
+/// A map whose obstacles are stored in a grid.
+///
+/// A wrapping map is a torus: its left and right edges are adjacent, as well as its top and bottom
+/// edges. Neighbors, distances and lines of sight go across the edges, so two consecutive
+/// waypoints of a path may be on both sides of the map and their distance is given by
+/// `Map::distance`, not by `path_length`.
+///
+/// With the `serde` feature, maps are serialized using the compact encoding of `GridMap::to_bytes`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(into = "CompactGridMap", try_from = "CompactGridMap"))]
 pub struct GridMap {
     obstacles: Grid<bool>,
+    frame: Frame,
+    wrapping: bool,
 }
 
 impl GridMap {
     pub fn new(obstacles: Grid<bool>) -> GridMap {
-        GridMap { obstacles }
+        GridMap::with_frame(obstacles, Frame::default())
+    }
+
+    pub fn with_frame(obstacles: Grid<bool>, frame: Frame) -> GridMap {
+        GridMap { obstacles, frame, wrapping: false }
+    }
+
+    pub fn with_wrapping(self, wrapping: bool) -> GridMap {
+        GridMap { wrapping, ..self }
+    }
+
+    pub fn obstacles(&self) -> &Grid<bool> {
+        &self.obstacles
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn wrapping(&self) -> bool {
+        self.wrapping
+    }
+
+    /// Returns the cell of the map at `point` once wrapped around the edges of the map, or `point`
+    /// itself if the map does not wrap.
+    pub fn wrap(&self, (x, y): &Point2D) -> Point2D {
+        if !self.wrapping {
+            return (*x, *y);
+        }
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries();
+        (
+            min_x + (x - min_x).rem_euclid(max_x - min_x),
+            min_y + (y - min_y).rem_euclid(max_y - min_y),
+        )
+    }
+
+    /// Returns the shortest offset from `a` to `b`, which may go across the edges of a wrapping map.
+    fn delta(&self, (ax, ay): &Point2D, (bx, by): &Point2D) -> Point2D {
+        if !self.wrapping {
+            return (bx - ax, by - ay);
+        }
+        let ((min_x, min_y), (max_x, max_y)) = self.boundaries();
+        (wrapped_delta(*ax, *bx, max_x - min_x), wrapped_delta(*ay, *by, max_y - min_y))
     }
 }
 
@@ -27,7 +143,22 @@ impl Map for GridMap {
     }
 
     fn line_of_sight(&self, start: &(isize, isize), end: &(isize, isize)) -> bool {
-        line_of_sight(start, end, &self.obstacles)
+        if !self.wrapping {
+            return line_of_sight(start, end, &self.obstacles);
+        }
+        let (dx, dy) = self.delta(start, end);
+        let end = (start.0 + dx, start.1 + dy);
+        line_of_sight_with(start, &end, |pos| self.obstacles.get(&self.wrap(pos)))
+    }
+
+    fn neighbors(&self, point: &Point2D) -> impl Iterator<Item=Point2D> {
+        let boundaries = if self.wrapping { NO_BOUNDARIES } else { self.boundaries() };
+        get_neighbors(*point, boundaries).map(move |pos| self.wrap(&pos))
+    }
+
+    fn distance(&self, a: &Point2D, b: &Point2D) -> f64 {
+        let (dx, dy) = self.delta(a, b);
+        euclidean_distance(&(0, 0), &(dx, dy))
     }
 }
 
@@ -37,3 +168,53 @@ impl Bounded for GridMap {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::find_path::find_path_impl;
+
+    use super::*;
+
+    fn wrapping_map() -> Result<GridMap, Box<dyn std::error::Error>> {
+        let map = GridMap::from_ascii("
+            ..........
+            ..#.......
+            ..#.......
+            ..#.......
+        ")?;
+        Ok(map.with_wrapping(true))
+    }
+
+    #[test]
+    fn test_wrapping_neighbors() -> Result<(), Box<dyn std::error::Error>> {
+        let map = wrapping_map()?;
+
+        let got: HashSet<Point2D> = map.neighbors(&(0, 0)).collect();
+        let want: HashSet<Point2D> = [(1, 0), (1, 1), (0, 1), (9, 1), (9, 0), (9, 3), (0, 3), (1, 3)].iter().cloned().collect();
+        assert_eq!(got, want);
+        assert_eq!(map.clone().with_wrapping(false).neighbors(&(0, 0)).count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping_distance_and_line_of_sight() -> Result<(), Box<dyn std::error::Error>> {
+        let map = wrapping_map()?;
+
+        assert_eq!(map.distance(&(0, 0), &(9, 0)), 1.);
+        assert_eq!(map.distance(&(1, 0), &(7, 3)), 17f64.sqrt());
+        assert!(map.line_of_sight(&(1, 0), &(7, 3)));
+        assert!(!map.clone().with_wrapping(false).line_of_sight(&(1, 0), &(7, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapping_path() -> Result<(), Box<dyn std::error::Error>> {
+        let map = wrapping_map()?;
+
+        assert_eq!(find_path_impl(&map, (1, 0), (7, 3))?, vec![(1, 0), (7, 3)]);
+        assert!(find_path_impl(&map.with_wrapping(false), (1, 0), (7, 3))?.len() > 2);
+        Ok(())
+    }
+}
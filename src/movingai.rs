@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ndarray::Array2;
+
+use crate::errors::MapFormatError;
+use crate::grid::Grid;
+use crate::map::GridMap;
+use crate::point::Point2D;
+
+/// One line of a MovingAI `.scen` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub bucket: usize,
+    pub map: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub start: Point2D,
+    pub goal: Point2D,
+    pub optimal_length: f64,
+}
+
+fn format_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("movingai: {}", msg)))
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&str>) -> Result<T, Box<dyn std::error::Error>> {
+    let value = value.ok_or_else(|| format_error("missing value"))?;
+    value.parse().map_err(|_| format_error(&format!("invalid number {:?}", value)))
+}
+
+/// Only `.` and `G` (ground) and `S` (swamp) cells can be traversed.
+fn is_obstacle(c: char) -> bool {
+    !matches!(c, '.' | 'G' | 'S')
+}
+
+/// Parses a MovingAI `.map` file.
+///
+/// Cells are indexed `(column, row)` with row 0 being the first line of the map, which is the
+/// coordinate system used by the `.scen` files.
+pub fn parse_movingai_map(content: &str) -> Result<GridMap, Box<dyn std::error::Error>> {
+    let mut lines = content.lines();
+    let mut width = None;
+    let mut height = None;
+
+    loop {
+        let line = lines.next().ok_or_else(|| format_error("missing map section"))?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("type") => {
+                if words.next() != Some("octile") {
+                    return Err(format_error("only octile maps are supported"));
+                }
+            }
+            Some("height") => height = Some(parse_number(words.next())?),
+            Some("width") => width = Some(parse_number(words.next())?),
+            Some("map") => break,
+            _ => return Err(format_error(&format!("unexpected header line {:?}", line))),
+        }
+    }
+
+    let width: usize = width.ok_or_else(|| format_error("missing width"))?;
+    let height: usize = height.ok_or_else(|| format_error("missing height"))?;
+
+    let mut obstacles = Array2::from_elem((width, height), true);
+    for y in 0..height {
+        let line = lines.next().ok_or_else(|| format_error("not enough rows"))?;
+        let row: Vec<char> = line.trim_end().chars().collect();
+        if row.len() != width {
+            return Err(format_error(&format!("row {} has {} cells, expected {}", y, row.len(), width)));
+        }
+        for (x, c) in row.into_iter().enumerate() {
+            obstacles[(x, y)] = is_obstacle(c);
+        }
+    }
+
+    Ok(GridMap::new(Grid::from(obstacles)))
+}
+
+/// Parses a MovingAI `.scen` file.
+pub fn parse_movingai_scenarios(content: &str) -> Result<Vec<Scenario>, Box<dyn std::error::Error>> {
+    let mut lines = content.lines();
+    match lines.next().map(str::split_whitespace).and_then(|mut w| w.next()) {
+        Some("version") => {}
+        _ => return Err(format_error("missing version header")),
+    }
+
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            Ok(Scenario {
+                bucket: parse_number(fields.next())?,
+                map: PathBuf::from(fields.next().ok_or_else(|| format_error("missing map"))?),
+                width: parse_number(fields.next())?,
+                height: parse_number(fields.next())?,
+                start: (parse_number(fields.next())?, parse_number(fields.next())?),
+                goal: (parse_number(fields.next())?, parse_number(fields.next())?),
+                optimal_length: parse_number(fields.next())?,
+            })
+        })
+        .collect()
+}
+
+pub fn load_movingai_map(path: impl AsRef<Path>) -> Result<GridMap, Box<dyn std::error::Error>> {
+    parse_movingai_map(&fs::read_to_string(path)?)
+}
+
+pub fn load_movingai_scenarios(path: impl AsRef<Path>) -> Result<Vec<Scenario>, Box<dyn std::error::Error>> {
+    parse_movingai_scenarios(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::{Bounded, Map};
+
+    use super::*;
+
+    const MAP: &str = "type octile\nheight 3\nwidth 4\nmap\n.@..\n.T.G\nS..W\n";
+
+    #[test]
+    fn test_parse_map() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_movingai_map(MAP)?;
+
+        assert_eq!(map.boundaries(), ((0, 0), (4, 3)));
+        assert!(!map.obstacle(&(0, 0)));
+        assert!(map.obstacle(&(1, 0)));
+        assert!(map.obstacle(&(1, 1)));
+        assert!(!map.obstacle(&(3, 1)));
+        assert!(!map.obstacle(&(0, 2)));
+        assert!(map.obstacle(&(3, 2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_truncated_map() {
+        assert!(parse_movingai_map("type octile\nheight 3\nwidth 4\nmap\n.@..\n").is_err());
+        assert!(parse_movingai_map("type octile\nheight 1\nwidth 4\nmap\n.@.\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_scenarios() -> Result<(), Box<dyn std::error::Error>> {
+        let scenarios = parse_movingai_scenarios(
+            "version 1\n0\tmaps/test.map\t4\t3\t0\t0\t2\t0\t3.41421356\n1\tmaps/test.map\t4\t3\t0\t2\t3\t1\t3.41421356\n",
+        )?;
+
+        assert_eq!(scenarios.len(), 2);
+        assert_eq!(scenarios[1], Scenario {
+            bucket: 1,
+            map: PathBuf::from("maps/test.map"),
+            width: 4,
+            height: 3,
+            start: (0, 2),
+            goal: (3, 1),
+            optimal_length: 3.41421356,
+        });
+        Ok(())
+    }
+}
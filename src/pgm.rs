@@ -0,0 +1,154 @@
+use crate::errors::MapFormatError;
+
+/// A grayscale image, `pixels` are stored row by row starting from the top row.
+pub(crate) struct Pgm {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) max_value: u16,
+    pub(crate) pixels: Vec<u16>,
+}
+
+impl Pgm {
+    pub(crate) fn get(&self, column: usize, row: usize) -> u16 {
+        self.pixels[row * self.width + column]
+    }
+}
+
+fn format_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("pgm: {}", msg)))
+}
+
+/// Reads the next whitespace separated header token, skipping comments.
+fn next_token<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < bytes.len() && bytes[*pos] == b'#' {
+            while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                *pos += 1;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return Err(format_error("unexpected end of file"));
+    }
+    Ok(&bytes[start..*pos])
+}
+
+fn next_number(bytes: &[u8], pos: &mut usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let token = next_token(bytes, pos)?;
+    std::str::from_utf8(token)?
+        .parse()
+        .map_err(|_| format_error("expected a number"))
+}
+
+pub(crate) fn read_pgm(bytes: &[u8]) -> Result<Pgm, Box<dyn std::error::Error>> {
+    let mut pos = 0;
+    let binary = match next_token(bytes, &mut pos)? {
+        b"P5" => true,
+        b"P2" => false,
+        _ => return Err(format_error("unsupported magic number")),
+    };
+    let width = next_number(bytes, &mut pos)?;
+    let height = next_number(bytes, &mut pos)?;
+    let max_value = next_number(bytes, &mut pos)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(format_error("invalid maximum value"));
+    }
+
+    let len = width.checked_mul(height).ok_or_else(|| format_error("invalid size"))?;
+    let pixels: Vec<u16> = if binary {
+        // A single whitespace character separates the header from the raster.
+        let data = bytes.get(pos + 1..).unwrap_or(&[]);
+        let pixel_size = if max_value < 256 { 1 } else { 2 };
+        let size = len.checked_mul(pixel_size).ok_or_else(|| format_error("invalid size"))?;
+        if data.len() < size {
+            return Err(format_error("truncated raster"));
+        }
+        data.chunks(pixel_size)
+            .take(len)
+            .map(|c| match c {
+                [v] => u16::from(*v),
+                [hi, lo] => u16::from(*hi) << 8 | u16::from(*lo),
+                _ => unreachable!(),
+            })
+            .collect()
+    } else {
+        (0..len)
+            .map(|_| next_number(bytes, &mut pos).map(|v| v as u16))
+            .collect::<Result<_, _>>()?
+    };
+
+    if pixels.iter().any(|v| usize::from(*v) > max_value) {
+        return Err(format_error("pixel value greater than maximum value"));
+    }
+
+    Ok(Pgm {
+        width,
+        height,
+        max_value: max_value as u16,
+        pixels,
+    })
+}
+
+/// Encodes the image as a binary (P5) PGM file.
+pub(crate) fn write_pgm(pgm: &Pgm) -> Vec<u8> {
+    let mut bytes = format!("P5\n{} {}\n{}\n", pgm.width, pgm.height, pgm.max_value).into_bytes();
+    for v in pgm.pixels.iter() {
+        if pgm.max_value < 256 {
+            bytes.push(*v as u8);
+        } else {
+            bytes.extend_from_slice(&v.to_be_bytes());
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_ascii_pgm() -> Result<(), Box<dyn std::error::Error>> {
+        let pgm = read_pgm(b"P2\n# a comment\n3 2\n255\n0 1 2\n3 4 255\n")?;
+
+        assert_eq!((pgm.width, pgm.height, pgm.max_value), (3, 2, 255));
+        assert_eq!(pgm.get(0, 0), 0);
+        assert_eq!(pgm.get(2, 0), 2);
+        assert_eq!(pgm.get(2, 1), 255);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let pgm = Pgm { width: 2, height: 2, max_value: 255, pixels: vec![0, 205, 254, 32] };
+
+        let got = read_pgm(&write_pgm(&pgm))?;
+        assert_eq!((got.width, got.height, got.max_value), (2, 2, 255));
+        assert_eq!(got.pixels, pgm.pixels);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_pgm() {
+        assert!(read_pgm(b"P5\n3 2\n255\n\x00\x01").is_err());
+    }
+
+    #[test]
+    fn test_overflowing_size() {
+        let huge = format!("P5\n{} {}\n255\n\x00", usize::MAX, 2);
+        assert!(read_pgm(huge.as_bytes()).is_err());
+        let huge = format!("P5\n{} {}\n65535\n\x00", usize::MAX / 2 + 1, 1);
+        assert!(read_pgm(huge.as_bytes()).is_err());
+        let huge = format!("P2\n{} {}\n255\n0", usize::MAX, 2);
+        assert!(read_pgm(huge.as_bytes()).is_err());
+    }
+}
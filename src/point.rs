@@ -6,6 +6,23 @@ pub fn euclidean_distance((ax, ay): &Point2D, (bx, by): &Point2D) -> f64 {
     ((ax - bx) * (ax - bx) + (ay - by) * (ay - by)).sqrt()
 }
 
+pub fn path_length(path: &[Point2D]) -> f64 {
+    path.windows(2)
+        .map(|w| euclidean_distance(&w[0], &w[1]))
+        .sum()
+}
+
 pub(crate) fn is_in_bounds((x, y): Point2D, ((min_x, min_y), (max_x, max_y)): (Point2D, Point2D)) -> bool {
     x < max_x && y < max_y && x >= min_x && y >= min_y
 }
+
+pub type Point3D = (isize, isize, isize);
+
+pub fn euclidean_distance_3d((ax, ay, az): &Point3D, (bx, by, bz): &Point3D) -> f64 {
+    let (dx, dy, dz) = ((ax - bx) as f64, (ay - by) as f64, (az - bz) as f64);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+pub(crate) fn is_in_bounds_3d((x, y, z): Point3D, ((min_x, min_y, min_z), (max_x, max_y, max_z)): (Point3D, Point3D)) -> bool {
+    x < max_x && y < max_y && z < max_z && x >= min_x && y >= min_y && z >= min_z
+}
@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyTuple};
+
+use crate::line_of_sight::line_of_sight_with;
+use crate::map::{Bounded, Map};
+use crate::point::Point2D;
+
+/// A map whose obstacles are given by python code, so procedurally generated maps can be searched
+/// without building an array first.
+///
+/// The python object is either a callable `obstacle(x, y) -> bool` or an object with such an
+/// `obstacle` method and optionally a `line_of_sight(x0, y0, x1, y1) -> bool` method, which
+/// replaces the default cell by cell check. Searches of this crate do not use cell costs, so
+/// objects with a `cost` method are rejected rather than searched as if all cells cost the same.
+///
+/// `Map` methods cannot fail: the first exception raised by the python code is kept, the failing
+/// cell is considered as an obstacle, and the exception is returned by `take_error` once the
+/// search is over.
+pub(crate) struct PyMap<'p> {
+    obstacle: &'p PyAny,
+    line_of_sight: Option<&'p PyAny>,
+    boundaries: (Point2D, Point2D),
+    error: RefCell<Option<PyErr>>,
+}
+
+impl<'p> PyMap<'p> {
+    pub fn new(object: &'p PyAny, boundaries: (Point2D, Point2D)) -> PyResult<PyMap<'p>> {
+        let obstacle = if object.hasattr("obstacle")? {
+            object.getattr("obstacle")?
+        } else if object.is_callable() {
+            object
+        } else {
+            return Err(exceptions::TypeError::py_err("map must be callable or have an obstacle method"));
+        };
+        if object.hasattr("cost")? {
+            return Err(exceptions::TypeError::py_err("map costs are not supported, only obstacles"));
+        }
+        let line_of_sight = if object.hasattr("line_of_sight")? {
+            Some(object.getattr("line_of_sight")?)
+        } else {
+            None
+        };
+
+        Ok(PyMap {
+            obstacle,
+            line_of_sight,
+            boundaries,
+            error: RefCell::new(None),
+        })
+    }
+
+    /// Returns the first exception raised by the python code, if any.
+    pub fn take_error(&self) -> Option<PyErr> {
+        self.error.borrow_mut().take()
+    }
+
+    fn call(&self, function: &PyAny, args: impl IntoPy<Py<PyTuple>>, on_error: bool) -> bool {
+        if self.error.borrow().is_some() {
+            return on_error;
+        }
+        match function.call1(args).and_then(|result| result.extract::<bool>()) {
+            Ok(result) => result,
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                on_error
+            }
+        }
+    }
+}
+
+impl Map for PyMap<'_> {
+    fn obstacle(&self, (x, y): &Point2D) -> bool {
+        self.call(self.obstacle, (*x, *y), true)
+    }
+
+    fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+        match self.line_of_sight {
+            Some(function) => self.call(function, (start.0, start.1, end.0, end.1), false),
+            None => line_of_sight_with(start, end, |pos| self.obstacle(pos)),
+        }
+    }
+}
+
+impl Bounded for PyMap<'_> {
+    fn boundaries(&self) -> (Point2D, Point2D) {
+        self.boundaries
+    }
+}
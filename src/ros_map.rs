@@ -0,0 +1,297 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::MapFormatError;
+use crate::frame::Frame;
+use crate::grid::Grid;
+use crate::map::{Bounded, GridMap};
+use crate::pgm::{Pgm, read_pgm, write_pgm};
+
+const FREE_PIXEL: u16 = 254;
+const OCCUPIED_PIXEL: u16 = 0;
+const UNKNOWN_PIXEL: u16 = 205;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Occupancy {
+    Free,
+    Occupied,
+    Unknown,
+}
+
+/// Content of a ROS `map_server` YAML file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RosMapMetadata {
+    pub image: PathBuf,
+    pub resolution: f64,
+    pub origin: (f64, f64, f64),
+    pub occupied_thresh: f64,
+    pub free_thresh: f64,
+    pub negate: bool,
+}
+
+impl RosMapMetadata {
+    pub fn parse(yaml: &str) -> Result<RosMapMetadata, Box<dyn std::error::Error>> {
+        let mut image = None;
+        let mut resolution = None;
+        let mut origin = None;
+        let mut occupied_thresh = 0.65;
+        let mut free_thresh = 0.196;
+        let mut negate = false;
+
+        for line in yaml.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() { continue; }
+
+            let mut kv = line.splitn(2, ':');
+            let key = kv.next().unwrap().trim();
+            let value = match kv.next() {
+                Some(v) => v.trim().trim_matches(|c| c == '"' || c == '\''),
+                None => return Err(yaml_error(&format!("invalid line {:?}", line))),
+            };
+
+            match key {
+                "image" => image = Some(PathBuf::from(value)),
+                "resolution" => resolution = Some(parse_f64(value)?),
+                "origin" => origin = Some(parse_origin(value)?),
+                "occupied_thresh" => occupied_thresh = parse_f64(value)?,
+                "free_thresh" => free_thresh = parse_f64(value)?,
+                "negate" => negate = parse_bool(value)?,
+                _ => {}
+            }
+        }
+
+        Ok(RosMapMetadata {
+            image: image.ok_or_else(|| yaml_error("missing image"))?,
+            resolution: resolution.ok_or_else(|| yaml_error("missing resolution"))?,
+            origin: origin.ok_or_else(|| yaml_error("missing origin"))?,
+            occupied_thresh,
+            free_thresh,
+            negate,
+        })
+    }
+
+    pub fn to_yaml(&self) -> String {
+        let (x, y, yaw) = self.origin;
+        format!(
+            "image: {}\nresolution: {}\norigin: [{}, {}, {}]\nnegate: {}\noccupied_thresh: {}\nfree_thresh: {}\n",
+            self.image.display(), self.resolution, x, y, yaw, self.negate as u8, self.occupied_thresh, self.free_thresh,
+        )
+    }
+
+    /// Classifies a pixel the same way `map_server` does in trinary mode.
+    fn occupancy(&self, value: u16, max_value: u16) -> Occupancy {
+        let value = f64::from(value) / f64::from(max_value);
+        let p = if self.negate { value } else { 1. - value };
+        if p > self.occupied_thresh {
+            Occupancy::Occupied
+        } else if p < self.free_thresh {
+            Occupancy::Free
+        } else {
+            Occupancy::Unknown
+        }
+    }
+}
+
+fn yaml_error(msg: &str) -> Box<dyn std::error::Error> {
+    Box::new(MapFormatError(format!("ros map yaml: {}", msg)))
+}
+
+fn parse_f64(value: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    value.parse().map_err(|_| yaml_error(&format!("invalid number {:?}", value)))
+}
+
+fn parse_bool(value: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    match value {
+        "0" | "false" | "False" => Ok(false),
+        "1" | "true" | "True" => Ok(true),
+        _ => Err(yaml_error(&format!("invalid boolean {:?}", value))),
+    }
+}
+
+fn parse_origin(value: &str) -> Result<(f64, f64, f64), Box<dyn std::error::Error>> {
+    let values = value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|v| parse_f64(v.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values.as_slice() {
+        [x, y, yaw] => Ok((*x, *y, *yaw)),
+        _ => Err(yaml_error("origin must be [x, y, yaw]")),
+    }
+}
+
+/// An occupancy map in the format used by the ROS `map_server`.
+///
+/// Cell `(0, 0)` is the bottom-left pixel of the image, as in ROS.
+pub struct RosMap {
+    pub metadata: RosMapMetadata,
+    pub occupancy: Grid<Occupancy>,
+}
+
+impl RosMap {
+    /// Loads the YAML file and the image it refers to.
+    pub fn load(yaml_path: impl AsRef<Path>) -> Result<RosMap, Box<dyn std::error::Error>> {
+        let yaml_path = yaml_path.as_ref();
+        let metadata = RosMapMetadata::parse(&fs::read_to_string(yaml_path)?)?;
+        let image_path = match yaml_path.parent() {
+            Some(dir) => dir.join(&metadata.image),
+            None => metadata.image.clone(),
+        };
+        let pgm = read_pgm(&fs::read(image_path)?)?;
+        Ok(RosMap::from_pgm(metadata, &pgm))
+    }
+
+    fn from_pgm(metadata: RosMapMetadata, pgm: &Pgm) -> RosMap {
+        let (width, height) = (pgm.width as isize, pgm.height as isize);
+        let mut occupancy = Grid::new(((0, 0), (width, height)), Occupancy::Unknown);
+        for row in 0..pgm.height {
+            for column in 0..pgm.width {
+                let value = pgm.get(column, row);
+                let cell = (column as isize, height - 1 - row as isize);
+                occupancy.set(&cell, metadata.occupancy(value, pgm.max_value));
+            }
+        }
+        RosMap { metadata, occupancy }
+    }
+
+    /// Builds a map from obstacles, every cell being either free or occupied.
+    pub fn from_grid_map(map: &GridMap, image: impl Into<PathBuf>) -> RosMap {
+        let frame = map.frame();
+        let (origin_x, origin_y) = frame.origin();
+        let metadata = RosMapMetadata {
+            image: image.into(),
+            resolution: frame.resolution(),
+            origin: (origin_x, origin_y, frame.rotation()),
+            occupied_thresh: 0.65,
+            free_thresh: 0.196,
+            negate: false,
+        };
+        let occupancy = map.obstacles().map(|obstacle| {
+            if obstacle { Occupancy::Occupied } else { Occupancy::Free }
+        });
+        RosMap { metadata, occupancy }
+    }
+
+    /// Writes the YAML file and the PGM image next to it, like `map_saver` does.
+    pub fn save(&self, yaml_path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let yaml_path = yaml_path.as_ref();
+        let image_path = match yaml_path.parent() {
+            Some(dir) => dir.join(&self.metadata.image),
+            None => self.metadata.image.clone(),
+        };
+        fs::write(image_path, write_pgm(&self.to_pgm()))?;
+        fs::write(yaml_path, self.metadata.to_yaml())?;
+        Ok(())
+    }
+
+    fn to_pgm(&self) -> Pgm {
+        let ((min_x, min_y), (max_x, max_y)) = self.occupancy.boundaries();
+        let (width, height) = ((max_x - min_x) as usize, (max_y - min_y) as usize);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in (min_y..max_y).rev() {
+            for x in min_x..max_x {
+                let value = match self.occupancy.get(&(x, y)) {
+                    Occupancy::Free => FREE_PIXEL,
+                    Occupancy::Occupied => OCCUPIED_PIXEL,
+                    Occupancy::Unknown => UNKNOWN_PIXEL,
+                };
+                pixels.push(if self.metadata.negate { 255 - value } else { value });
+            }
+        }
+        Pgm { width, height, max_value: 255, pixels }
+    }
+
+    pub fn frame(&self) -> Frame {
+        let (x, y, yaw) = self.metadata.origin;
+        Frame::new((x, y), self.metadata.resolution).with_rotation(yaw)
+    }
+
+    pub fn to_grid_map(&self, unknown_is_obstacle: bool) -> GridMap {
+        let obstacles = self.occupancy.map(|occupancy| match occupancy {
+            Occupancy::Free => false,
+            Occupancy::Occupied => true,
+            Occupancy::Unknown => unknown_is_obstacle,
+        });
+        GridMap::with_frame(obstacles, self.frame())
+    }
+
+    /// Returns the cost of traversing each cell: free cells cost nothing, occupied cells cannot be
+    /// traversed and unknown cells cost `unknown_cost`.
+    pub fn cost_grid(&self, unknown_cost: f64) -> Grid<f64> {
+        self.occupancy.map(|occupancy| match occupancy {
+            Occupancy::Free => 0.,
+            Occupancy::Occupied => f64::INFINITY,
+            Occupancy::Unknown => unknown_cost,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    const YAML: &str = "image: testmap.pgm\n\
+                        resolution: 0.050000\n\
+                        origin: [-12.5, 3.0, 0.0]  # comment\n\
+                        negate: 0\n\
+                        occupied_thresh: 0.65\n\
+                        free_thresh: 0.196\n";
+
+    #[test]
+    fn test_parse_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let metadata = RosMapMetadata::parse(YAML)?;
+
+        assert_eq!(metadata, RosMapMetadata {
+            image: PathBuf::from("testmap.pgm"),
+            resolution: 0.05,
+            origin: (-12.5, 3.0, 0.0),
+            occupied_thresh: 0.65,
+            free_thresh: 0.196,
+            negate: false,
+        });
+        assert_eq!(RosMapMetadata::parse(&metadata.to_yaml())?, metadata);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_resolution() {
+        assert!(RosMapMetadata::parse("image: a.pgm\norigin: [0, 0, 0]\n").is_err());
+    }
+
+    #[test]
+    fn test_occupancy_from_pgm() -> Result<(), Box<dyn std::error::Error>> {
+        let pgm = read_pgm(b"P2\n3 2\n255\n0 254 205\n254 254 10\n")?;
+        let map = RosMap::from_pgm(RosMapMetadata::parse(YAML)?, &pgm);
+
+        assert_eq!(map.occupancy.boundaries(), ((0, 0), (3, 2)));
+        assert_eq!(map.occupancy.get(&(0, 1)), Occupancy::Occupied);
+        assert_eq!(map.occupancy.get(&(1, 1)), Occupancy::Free);
+        assert_eq!(map.occupancy.get(&(2, 1)), Occupancy::Unknown);
+        assert_eq!(map.occupancy.get(&(2, 0)), Occupancy::Occupied);
+
+        let grid_map = map.to_grid_map(true);
+        assert!(grid_map.obstacles().get(&(2, 1)));
+        assert_eq!(grid_map.frame().origin(), (-12.5, 3.0));
+        assert_eq!(map.cost_grid(3.).get(&(2, 1)), 3.);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_and_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("grid_pathfinding_ros_map_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let pgm = read_pgm(b"P2\n3 2\n255\n0 254 205\n254 254 10\n")?;
+        let map = RosMap::from_pgm(RosMapMetadata::parse(YAML)?, &pgm);
+
+        map.save(dir.join("testmap.yaml"))?;
+        let got = RosMap::load(dir.join("testmap.yaml"))?;
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(got.metadata, map.metadata);
+        assert_eq!(got.occupancy.as_array(), map.occupancy.as_array());
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use bresenham::Bresenham;
+
+use crate::map::Map;
+use crate::point::Point2D;
+
+/// Removes the redundant waypoints of a path greedily: from each waypoint, the path goes straight
+/// to the following waypoints as long as they are in line of sight, and turns at the last one
+/// before the first waypoint out of sight.
+///
+/// The path keeps its first and last waypoints and is never longer than the original one. This
+/// removes the staircases of paths going from cell to adjacent cell, with about one line of sight
+/// check per waypoint.
+pub fn smooth_path_impl(map: &impl Map, path: &[Point2D]) -> Vec<Point2D> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+
+    let mut smoothed = vec![path[0]];
+    let mut anchor = 0;
+    while anchor < path.len() - 1 {
+        let mut next = anchor + 1;
+        while next + 1 < path.len() && map.line_of_sight(&path[anchor], &path[next + 1]) {
+            next += 1;
+        }
+        smoothed.push(path[next]);
+        anchor = next;
+    }
+    smoothed
+}
+
+/// Pulls a path taut around the obstacles: the path is first densified into all the cells of the
+/// straight lines between its waypoints, then smoothed by `smooth_path_impl`. Waypoints can thus
+/// move to any of these cells, so the path turns next to the corners of the obstacles instead of
+/// where the original path did.
+///
+/// This is a greedy shortcut pass over the cells of the path rather than an exact funnel: the
+/// path is not always the shortest one in the corridor of these cells. It takes one line of sight
+/// check per cell.
+///
+/// The cells between two waypoints are those of their straight line, so paths going across the
+/// edges of wrapping maps are not supported.
+pub fn pull_string(map: &impl Map, path: &[Point2D]) -> Vec<Point2D> {
+    let cells: Vec<Point2D> = path.windows(2)
+        .flat_map(|w| Bresenham::new(w[0], w[1]))
+        .chain(path.last().copied())
+        .collect();
+    smooth_path_impl(map, &cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::dstar_lite::DStarLite;
+    use crate::grid::Grid;
+    use crate::map::{Bounded, GridMap};
+
+    use super::*;
+
+    fn cost(map: &impl Map, path: &[Point2D]) -> f64 {
+        path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum()
+    }
+
+    #[test]
+    fn test_smooth_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.........
+            ..........
+            ...####...
+            ...####...
+            .........G
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let path = DStarLite::new(&map, start, goal).plan(&map)?;
+
+        let smoothed = smooth_path_impl(&map, &path);
+        assert_eq!(smoothed.first(), Some(&start));
+        assert_eq!(smoothed.last(), Some(&goal));
+        assert!(smoothed.len() < path.len());
+        assert!(smoothed.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(cost(&map, &smoothed) <= cost(&map, &path) + 1e-9);
+
+        assert_eq!(smooth_path_impl(&map, &[start]), vec![start]);
+        assert_eq!(smooth_path_impl(&map, &[(0, 0), (1, 0), (2, 0)]), vec![(0, 0), (2, 0)]);
+        Ok(())
+    }
+
+    /// A map counting its line of sight checks.
+    struct CountingMap {
+        map: GridMap,
+        checks: Cell<usize>,
+    }
+
+    impl Map for CountingMap {
+        fn obstacle(&self, point: &Point2D) -> bool {
+            self.map.obstacle(point)
+        }
+
+        fn line_of_sight(&self, start: &Point2D, end: &Point2D) -> bool {
+            self.checks.set(self.checks.get() + 1);
+            self.map.line_of_sight(start, end)
+        }
+    }
+
+    impl Bounded for CountingMap {
+        fn boundaries(&self) -> (Point2D, Point2D) {
+            self.map.boundaries()
+        }
+    }
+
+    #[test]
+    fn test_linear_checks() {
+        // A snake of corridors separated by walls open at alternate ends, and the path along it.
+        let (width, height) = (30, 41);
+        let mut obstacles = Grid::new(((0, 0), (width, height)), false);
+        let mut path = Vec::new();
+        for y in 0..height {
+            if y % 2 == 1 {
+                let gap = if y % 4 == 1 { width - 1 } else { 0 };
+                (0..width).filter(|x| *x != gap).for_each(|x| obstacles.set(&(x, y), true));
+                path.push((gap, y));
+            } else if y % 4 == 0 {
+                path.extend((0..width).map(|x| (x, y)));
+            } else {
+                path.extend((0..width).rev().map(|x| (x, y)));
+            }
+        }
+        let map = CountingMap { map: GridMap::new(obstacles), checks: Cell::new(0) };
+
+        let smoothed = smooth_path_impl(&map, &path);
+        assert!(map.checks.get() <= 2 * path.len());
+        assert_eq!(smoothed.first(), path.first());
+        assert_eq!(smoothed.last(), path.last());
+        assert!(smoothed.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(smoothed.len() < path.len() / 4);
+    }
+
+    #[test]
+    fn test_pull_string() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ..........
+            ..........
+            ...####...
+            ...####...
+            ..........
+        ")?.map;
+        // Goes around the obstacle far from its corners.
+        let path = vec![(0, 4), (0, 0), (9, 0)];
+
+        let smoothed = smooth_path_impl(&map, &path);
+        let pulled = pull_string(&map, &path);
+        assert_eq!(pulled.first(), Some(&(0, 4)));
+        assert_eq!(pulled.last(), Some(&(9, 0)));
+        assert!(pulled.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(cost(&map, &pulled) < cost(&map, &smoothed));
+        Ok(())
+    }
+}
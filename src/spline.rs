@@ -0,0 +1,231 @@
+use crate::errors::BlockedSegmentError;
+use crate::map::Map;
+use crate::point::{is_in_bounds, Point2D};
+
+/// A point of a trajectory, in cells: `(x, y)` is the center of the cell `(x, y)`.
+pub type TrajectoryPoint = (f64, f64);
+
+/// Largest distance along the curve between two points checked against the obstacles, in cells.
+const CHECK_STEP: f64 = 0.25;
+/// Increase of the tension of a waypoint whose curve goes through obstacles.
+const TENSION_STEP: f64 = 0.125;
+
+/// A cubic Hermite segment from `p1` to `p2`, with tangents `m1` and `m2`.
+struct Segment {
+    p1: TrajectoryPoint,
+    p2: TrajectoryPoint,
+    m1: TrajectoryPoint,
+    m2: TrajectoryPoint,
+}
+
+impl Segment {
+    /// Returns the point at `t` in `[0, 1]`.
+    fn at(&self, t: f64) -> TrajectoryPoint {
+        let (t2, t3) = (t * t, t * t * t);
+        let (h00, h10, h01, h11) = (2. * t3 - 3. * t2 + 1., t3 - 2. * t2 + t, 3. * t2 - 2. * t3, t3 - t2);
+        let interpolate = |p1: f64, m1: f64, p2: f64, m2: f64| h00 * p1 + h10 * m1 + h01 * p2 + h11 * m2;
+        (
+            interpolate(self.p1.0, self.m1.0, self.p2.0, self.m2.0),
+            interpolate(self.p1.1, self.m1.1, self.p2.1, self.m2.1),
+        )
+    }
+
+    /// Returns an upper bound of the speed of the point along the segment as `t` goes from 0 to 1:
+    /// 3 times the longest side of its Bezier control polygon.
+    fn max_speed(&self) -> f64 {
+        let ((x1, y1), (x2, y2)) = (self.p1, self.p2);
+        let ((mx1, my1), (mx2, my2)) = (self.m1, self.m2);
+        let middle = (x2 - x1 - (mx1 + mx2) / 3., y2 - y1 - (my1 + my2) / 3.);
+        mx1.hypot(my1).max(3. * middle.0.hypot(middle.1)).max(mx2.hypot(my2))
+    }
+
+    /// Returns whether the segment stays away from the obstacles and the edges of the map.
+    ///
+    /// Checked points are at most `CHECK_STEP` apart along the curve, so every point of the curve
+    /// is in the square of side `CHECK_STEP` around a checked point, whose corners are checked.
+    fn free(&self, map: &impl Map) -> bool {
+        let checks = (self.max_speed() / CHECK_STEP).ceil().max(1.) as usize;
+        let margin = CHECK_STEP / 2.;
+        (0..=checks)
+            .map(|k| self.at(k as f64 / checks as f64))
+            .flat_map(|(x, y)| [(x - margin, y - margin), (x - margin, y + margin), (x + margin, y - margin), (x + margin, y + margin)])
+            .map(|(x, y)| (x.round() as isize, y.round() as isize))
+            .all(|cell| is_in_bounds(cell, map.boundaries()) && !map.obstacle(&cell))
+    }
+}
+
+/// Returns the segments of the cardinal spline through the waypoints of `path`. The tangent at
+/// each waypoint is that of the Catmull-Rom spline scaled by `1 - tension`: the higher the tension,
+/// the sharper the turn, down to a straight segment between two waypoints of tension 1.
+fn segments(path: &[Point2D], tensions: &[f64]) -> Vec<Segment> {
+    let to_point = |(x, y): &Point2D| (*x as f64, *y as f64);
+    let tangents: Vec<TrajectoryPoint> = (0..path.len())
+        .map(|i| {
+            let ((x0, y0), (x2, y2)) = (to_point(&path[i.saturating_sub(1)]), to_point(&path[(i + 1).min(path.len() - 1)]));
+            let scale = 0.5 * (1. - tensions[i]);
+            (scale * (x2 - x0), scale * (y2 - y0))
+        })
+        .collect();
+    (0..path.len() - 1)
+        .map(|i| Segment { p1: to_point(&path[i]), p2: to_point(&path[i + 1]), m1: tangents[i], m2: tangents[i + 1] })
+        .collect()
+}
+
+/// Turns the waypoints of a path into a smooth trajectory sampled about every `step` cells, e.g.
+/// for vehicles which cannot turn sharply at the waypoints.
+///
+/// The trajectory is a cardinal spline going through every waypoint, whose direction changes
+/// continuously. Each segment is checked against the obstacles and the edges of the map at least
+/// every quarter of a cell, whatever `step` is, and must not come closer to them than an eighth of
+/// a cell. The waypoints of segments going through obstacles get a higher tension, i.e. a tighter
+/// turn, until the segments are free, which may end with a sharp turn at a waypoint when there is
+/// no room for a curve. Returns an error when a segment is still not free once straight, so
+/// waypoints must be in line of sight, as the ones returned by `find_path_impl`. Use `curvatures`
+/// to check the turns of the trajectory against the limits of a vehicle.
+pub fn spline_path_impl(map: &impl Map, path: &[Point2D], step: f64) -> Result<Vec<TrajectoryPoint>, Box<dyn std::error::Error>> {
+    let to_point = |(x, y): &Point2D| (*x as f64, *y as f64);
+    if path.len() < 2 {
+        return Ok(path.iter().map(to_point).collect());
+    }
+
+    let mut tensions = vec![0.; path.len()];
+    let segments = loop {
+        let segments = segments(path, &tensions);
+        let blocked: Vec<usize> = (0..segments.len()).filter(|i| !segments[*i].free(map)).collect();
+        if blocked.is_empty() {
+            break segments;
+        }
+        for i in blocked {
+            if tensions[i] == 1. && tensions[i + 1] == 1. {
+                return Err(Box::new(BlockedSegmentError(path[i], path[i + 1])));
+            }
+            for tension in tensions[i..=i + 1].iter_mut() {
+                *tension = (*tension + TENSION_STEP).min(1.);
+            }
+        }
+    };
+
+    let step = if step > 0. { step } else { 1. };
+    let mut trajectory = vec![to_point(&path[0])];
+    for (segment, w) in segments.iter().zip(path.windows(2)) {
+        let samples = (map.distance(&w[0], &w[1]) / step).ceil().max(1.) as usize;
+        trajectory.extend((1..=samples).map(|k| segment.at(k as f64 / samples as f64)));
+    }
+    Ok(trajectory)
+}
+
+/// Returns the curvature of a trajectory at each of its points but the first and last ones: the
+/// inverse of the radius of the circle through the point and its two neighbors, 0 on straight
+/// lines and infinite on sharp turns.
+pub fn curvatures(trajectory: &[TrajectoryPoint]) -> Vec<f64> {
+    trajectory.windows(3)
+        .map(|w| {
+            let ((ax, ay), (bx, by), (cx, cy)) = (w[0], w[1], w[2]);
+            let cross = (bx - ax) * (cy - ay) - (by - ay) * (cx - ax);
+            let sides = (bx - ax).hypot(by - ay) * (cx - bx).hypot(cy - by) * (cx - ax).hypot(cy - ay);
+            if sides == 0. { 0. } else { 2. * cross.abs() / sides }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::parse_ascii;
+    use crate::grid::Grid;
+    use crate::map::GridMap;
+
+    use super::*;
+
+    fn free(map: &impl Map, trajectory: &[TrajectoryPoint]) -> bool {
+        trajectory.iter().all(|(x, y)| {
+            let cell = (x.round() as isize, y.round() as isize);
+            is_in_bounds(cell, map.boundaries()) && !map.obstacle(&cell)
+        })
+    }
+
+    /// Returns whether the direction of a densely sampled trajectory never turns sharply.
+    fn smooth(trajectory: &[TrajectoryPoint]) -> bool {
+        trajectory.windows(3).all(|w| {
+            let ((ax, ay), (bx, by), (cx, cy)) = (w[0], w[1], w[2]);
+            let (u, v) = ((bx - ax, by - ay), (cx - bx, cy - by));
+            (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1).abs() < 0.1
+        })
+    }
+
+    #[test]
+    fn test_spline_path() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ..........
+            ..........
+            ..........
+            ....##....
+            ....##....
+            ..........
+            ..........
+            ..........
+            ..........
+        ")?.map;
+        let path = vec![(0, 0), (8, 0), (8, 8)];
+
+        let trajectory = spline_path_impl(&map, &path, 0.5)?;
+        assert_eq!(trajectory.first(), Some(&(0., 0.)));
+        assert_eq!(trajectory.last(), Some(&(8., 8.)));
+        assert!(path.iter().all(|(x, y)| trajectory.contains(&(*x as f64, *y as f64))));
+        assert!(trajectory.windows(2).all(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1) <= 0.75));
+        let dense = spline_path_impl(&map, &path, 0.01)?;
+        assert!(free(&map, &dense));
+        assert!(smooth(&dense));
+
+        // The first segment would go below the map and is tightened, the second one still bends.
+        assert!(trajectory.iter().any(|(x, _)| *x > 8.2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_obstacle_between_samples() -> Result<(), Box<dyn std::error::Error>> {
+        let mut obstacles = Grid::new(((0, 0), (11, 11)), false);
+        obstacles.set(&(10, 5), true);
+        let map = GridMap::new(obstacles);
+        let path = vec![(1, 1), (9, 1), (9, 9), (1, 9)];
+
+        // Samples are 3 cells apart, the curve bending around (9, 5) must still avoid (10, 5).
+        let trajectory = spline_path_impl(&map, &path, 3.)?;
+        let dense = spline_path_impl(&map, &path, 0.01)?;
+        assert!(free(&map, &trajectory));
+        assert!(free(&map, &dense));
+        assert!(dense.iter().any(|(x, _)| *x > 9.1));
+        // The curve is tightened rather than straightened, so its direction changes smoothly.
+        assert!(smooth(&dense));
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("...")?.map;
+        assert_eq!(spline_path_impl(&map, &[(1, 0)], 0.5)?, vec![(1., 0.)]);
+        assert_eq!(spline_path_impl(&map, &[(0, 0), (2, 0)], 1.)?, vec![(0., 0.), (1., 0.), (2., 0.)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocked_segment() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            .#.
+            ...
+        ")?.map;
+        // Even straight, the segment goes through the obstacle.
+        assert!(spline_path_impl(&map, &[(0, 0), (2, 1)], 0.5).is_err());
+        // Around the obstacle, the turn is tightened until it is free.
+        assert!(free(&map, &spline_path_impl(&map, &[(0, 0), (2, 0), (2, 1)], 0.01)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_curvatures() {
+        assert_eq!(curvatures(&[(0., 0.), (1., 0.)]), vec![]);
+        assert_eq!(curvatures(&[(0., 0.), (1., 0.), (2., 0.), (2., 0.)]), vec![0., 0.]);
+        // Points of a circle of radius 2.
+        let got = curvatures(&[(2., 0.), (0., 2.), (-2., 0.)]);
+        assert!((got[0] - 0.5).abs() < 1e-9);
+    }
+}
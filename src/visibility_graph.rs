@@ -0,0 +1,220 @@
+use std::collections::BinaryHeap;
+
+use crate::errors::NoPathFoundError;
+use crate::heap::HeapElement;
+use crate::map::Map;
+use crate::point::{is_in_bounds, Point2D};
+
+/// Returns the free cells at the convex corners of the obstacles, where shortest any-angle paths
+/// turn: cells with an obstacle on a diagonal and no obstacle on the two sides of this diagonal,
+/// and cells along the end of a wall, since lines of sight may go along walls.
+pub fn convex_corners(map: &impl Map) -> Vec<Point2D> {
+    let boundaries = map.boundaries();
+    let ((min_x, min_y), (max_x, max_y)) = boundaries;
+    let obstacle = |pos: Point2D| is_in_bounds(pos, boundaries) && map.obstacle(&pos);
+
+    let mut corners = Vec::new();
+    for x in min_x..max_x {
+        for y in min_y..max_y {
+            if map.obstacle(&(x, y)) { continue; }
+            let diagonal_corner = [(-1, -1), (-1, 1), (1, -1), (1, 1)].iter()
+                .any(|(dx, dy)| obstacle((x + dx, y + dy)) && !obstacle((x + dx, y)) && !obstacle((x, y + dy)));
+            let wall_end = [((0, 1), (1, 0)), ((1, 0), (0, 1))].iter()
+                .flat_map(|&((dx, dy), (px, py))| [
+                    ((dx, dy), (px, py)), ((dx, dy), (-px, -py)), ((-dx, -dy), (px, py)), ((-dx, -dy), (-px, -py)),
+                ])
+                .any(|((dx, dy), (px, py))| {
+                    obstacle((x + dx, y + dy)) && !obstacle((x + px, y + py)) && !obstacle((x + dx + px, y + dy + py))
+                });
+            let is_corner = diagonal_corner || wall_end;
+            if is_corner {
+                corners.push((x, y));
+            }
+        }
+    }
+    corners
+}
+
+/// A visibility graph over the convex corners of the obstacles of a map, which finds the shortest
+/// any-angle paths turning only at these corners.
+///
+/// Building the graph checks the lines of sight between all pairs of corners, which is only worth
+/// it for static maps: the graph is then built once and reused by all the queries. Each query
+/// only checks the lines of sight from its start and goal to the corners.
+pub struct VisibilityGraph {
+    corners: Vec<Point2D>,
+    /// Corners visible from each corner, with their distance.
+    edges: Vec<Vec<(usize, f64)>>,
+}
+
+impl VisibilityGraph {
+    pub fn new(map: &impl Map) -> VisibilityGraph {
+        let corners = convex_corners(map);
+        let mut edges = vec![Vec::new(); corners.len()];
+        for i in 0..corners.len() {
+            for j in 0..corners.len() {
+                // Lines of sight are not always symmetric, so edges are checked both ways.
+                if i != j && map.line_of_sight(&corners[i], &corners[j]) {
+                    edges[i].push((j, map.distance(&corners[i], &corners[j])));
+                }
+            }
+        }
+        VisibilityGraph { corners, edges }
+    }
+
+    pub fn corners(&self) -> &[Point2D] {
+        &self.corners
+    }
+
+    /// Searches the shortest any-angle path between two cells, `map` being the map the graph was
+    /// built from.
+    ///
+    /// The search is guided by `Map::distance` rather than `Map::heuristic`, which may exceed the
+    /// length of any-angle paths, e.g. for a `LandmarkMap`.
+    pub fn find_path(&self, map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+        if map.obstacle(&start) || map.obstacle(&end) {
+            return Err(Box::new(NoPathFoundError()));
+        }
+        if map.line_of_sight(&start, &end) {
+            return Ok(vec![start, end]);
+        }
+
+        // The start and the end are the two nodes following the corners.
+        let (start_node, end_node) = (self.corners.len(), self.corners.len() + 1);
+        let position = |node: usize| match node {
+            n if n == start_node => start,
+            n if n == end_node => end,
+            n => self.corners[n],
+        };
+        let start_edges: Vec<(usize, f64)> = self.corners.iter()
+            .enumerate()
+            .filter(|(_, corner)| map.line_of_sight(&start, corner))
+            .map(|(i, corner)| (i, map.distance(&start, corner)))
+            .collect();
+        // Distance from each node to the end, if in line of sight.
+        let to_end: Vec<Option<f64>> = self.corners.iter()
+            .map(|corner| map.line_of_sight(corner, &end).then(|| map.distance(corner, &end)))
+            .chain([None, None])
+            .collect();
+
+        let mut open_set: BinaryHeap<HeapElement<usize>> = BinaryHeap::with_capacity(self.corners.len() + 2);
+        let mut g_score = vec![f64::INFINITY; self.corners.len() + 2];
+        let mut came_from: Vec<Option<usize>> = vec![None; self.corners.len() + 2];
+        g_score[start_node] = 0.;
+        open_set.push(HeapElement { position: start_node, f_score: map.distance(&start, &end) });
+
+        while let Some(HeapElement { position: node, f_score }) = open_set.pop() {
+            let g = g_score[node];
+            if f_score > g + map.distance(&position(node), &end) { continue; }
+            if node == end_node {
+                let mut path = vec![end];
+                let mut node = end_node;
+                while let Some(previous) = came_from[node] {
+                    path.push(position(previous));
+                    node = previous;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+
+            let edges = if node == start_node { &start_edges } else { &self.edges[node] };
+            let to_end = to_end[node].map(|distance| (end_node, distance));
+            for (neighbor, distance) in edges.iter().copied().chain(to_end) {
+                let tentative_g_score = g + distance;
+                if tentative_g_score < g_score[neighbor] {
+                    g_score[neighbor] = tentative_g_score;
+                    came_from[neighbor] = Some(node);
+                    let new_f_score = tentative_g_score + map.distance(&position(neighbor), &end);
+                    open_set.push(HeapElement { position: neighbor, f_score: new_f_score });
+                }
+            }
+        }
+        Err(Box::new(NoPathFoundError()))
+    }
+}
+
+/// Same as `find_path_impl` but finds the shortest any-angle path by building the visibility graph
+/// of `map`. Build a `VisibilityGraph` once instead to answer several queries on a static map.
+pub fn find_path_visibility(map: &impl Map, start: Point2D, end: Point2D) -> Result<Vec<Point2D>, Box<dyn std::error::Error>> {
+    VisibilityGraph::new(map).find_path(map, start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::{AsciiMap, parse_ascii};
+    use crate::find_path::find_path_impl;
+    use crate::landmarks::{LandmarkMap, Landmarks};
+
+    use super::*;
+
+    fn cost(map: &impl Map, path: &[Point2D]) -> f64 {
+        path.windows(2).map(|w| map.distance(&w[0], &w[1])).sum()
+    }
+
+    #[test]
+    fn test_convex_corners() -> Result<(), Box<dyn std::error::Error>> {
+        let map = parse_ascii("
+            ......
+            .####.
+            ......
+        ")?.map;
+        let mut corners = convex_corners(&map);
+        corners.sort();
+        assert_eq!(corners, vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 2), (4, 0), (4, 2), (5, 0), (5, 1), (5, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shortest_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#.......
+            ..#.####..
+            ..#....#..
+            ..####.#..
+            .......#.G
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        let graph = VisibilityGraph::new(&map);
+
+        let path = graph.find_path(&map, start, goal)?;
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        assert!(path[1..path.len() - 1].iter().all(|p| graph.corners().contains(p)));
+        assert!(cost(&map, &path) <= cost(&map, &find_path_impl(&map, start, goal)?) + 1e-9);
+        assert_eq!(find_path_visibility(&map, start, goal)?, path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_landmark_map() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            ...#.#.....G
+            .....#..#...
+            #...#.......
+            ....#....#.#
+            .....#.#...#
+            .......###..
+            ...#.....#..
+            S.#....#.#..
+        ")?;
+        let (start, goal) = (start.unwrap(), goal.unwrap());
+        // Landmarks overestimate any-angle lengths, which must not change the path found.
+        let landmarks = Landmarks::with_positions(&map, &[(11, 0), (0, 0)]);
+        let guided = LandmarkMap::new(&map, &landmarks);
+
+        let path = find_path_visibility(&guided, start, goal)?;
+        assert_eq!(path, find_path_visibility(&map, start, goal)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_path() -> Result<(), Box<dyn std::error::Error>> {
+        let AsciiMap { map, start, goal } = parse_ascii("
+            S.#..
+            ..#.G
+        ")?;
+        assert!(find_path_visibility(&map, start.unwrap(), goal.unwrap()).is_err());
+        Ok(())
+    }
+}
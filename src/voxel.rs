@@ -0,0 +1,181 @@
+use std::collections::BinaryHeap;
+
+use crate::errors::NoPathFoundError;
+use crate::grid3d::Grid3D;
+use crate::heap::HeapElement;
+use crate::point::{euclidean_distance_3d, is_in_bounds_3d, Point3D};
+
+/// Returns the 26 cells surrounding `pos` which are in the boundaries.
+pub(crate) fn get_neighbors_3d(pos: Point3D, boundaries: (Point3D, Point3D)) -> impl Iterator<Item=Point3D> {
+    let (x, y, z) = pos;
+    (-1..=1).flat_map(move |dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (x + dx, y + dy, z + dz))))
+        .filter(move |n| *n != pos && is_in_bounds_3d(*n, boundaries))
+}
+
+/// Returns the cells of the 3D Bresenham line between `start` and `end`, both included.
+pub fn line_3d(start: &Point3D, end: &Point3D) -> impl Iterator<Item=Point3D> {
+    let (sx, sy, sz) = *start;
+    let (dx, dy, dz) = (end.0 - sx, end.1 - sy, end.2 - sz);
+    let n = dx.abs().max(dy.abs()).max(dz.abs());
+    // Rounds `delta * i / n` to the nearest integer.
+    let step = move |delta: isize, i: isize| (2 * delta * i + n).div_euclid(2 * n);
+    (0..=n).map(move |i| match n {
+        0 => (sx, sy, sz),
+        _ => (sx + step(dx, i), sy + step(dy, i), sz + step(dz, i)),
+    })
+}
+
+/// A 3D map whose obstacles are stored in a voxel grid.
+pub struct VoxelMap {
+    obstacles: Grid3D<bool>,
+}
+
+impl VoxelMap {
+    pub fn new(obstacles: Grid3D<bool>) -> VoxelMap {
+        VoxelMap { obstacles }
+    }
+
+    pub fn obstacles(&self) -> &Grid3D<bool> {
+        &self.obstacles
+    }
+
+    pub fn boundaries(&self) -> (Point3D, Point3D) {
+        self.obstacles.boundaries()
+    }
+
+    pub fn obstacle(&self, point: &Point3D) -> bool {
+        self.obstacles.get(point)
+    }
+
+    pub fn line_of_sight(&self, start: &Point3D, end: &Point3D) -> bool {
+        !line_3d(start, end).any(|pos| self.obstacles.get(&pos))
+    }
+}
+
+/// Searches an any-angle path between two cells of a voxel map with Lazy Theta*: cells are
+/// expanded as in A* over the 26-connected grid, but each cell keeps the furthest ancestor it can
+/// see as its parent.
+pub fn find_path_3d_impl(map: &VoxelMap, start: Point3D, end: Point3D) -> Result<Vec<Point3D>, Box<dyn std::error::Error>> {
+    if map.obstacle(&start) || map.obstacle(&end) {
+        return Err(Box::new(NoPathFoundError()));
+    }
+    if map.line_of_sight(&start, &end) {
+        return Ok(vec![start, end]);
+    }
+
+    let boundaries = map.boundaries();
+    let mut open_set: BinaryHeap<HeapElement<Point3D>> = BinaryHeap::with_capacity(1024);
+    let mut g_score: Grid3D<f64> = Grid3D::new(boundaries, f64::INFINITY);
+    let mut came_from: Grid3D<Option<Point3D>> = Grid3D::new(boundaries, None);
+    let mut closed: Grid3D<bool> = Grid3D::new(boundaries, false);
+
+    let heuristic = |pos: &Point3D| euclidean_distance_3d(pos, &end);
+
+    g_score.set(&start, 0.);
+    came_from.set(&start, Some(start));
+    open_set.push(HeapElement { position: start, f_score: heuristic(&start) });
+
+    while let Some(HeapElement { position, .. }) = open_set.pop() {
+        if closed.get(&position) { continue; }
+
+        let parent = came_from.get(&position).unwrap();
+        if !map.line_of_sight(&parent, &position) {
+            let (neighbor, neighbor_g_score) = get_neighbors_3d(position, boundaries)
+                .filter(|n| closed.get(n))
+                .map(|n| (n, g_score.get(&n) + euclidean_distance_3d(&n, &position)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap();
+            g_score.set(&position, neighbor_g_score);
+            came_from.set(&position, Some(neighbor));
+        }
+
+        if position == end {
+            return Ok(build_path(&came_from, &start, &end));
+        }
+        closed.set(&position, true);
+
+        let parent = came_from.get(&position).unwrap();
+        for neighbor in get_neighbors_3d(position, boundaries) {
+            if closed.get(&neighbor) || map.obstacle(&neighbor) { continue; }
+
+            let tentative_g_score = g_score.get(&parent) + euclidean_distance_3d(&parent, &neighbor);
+            if tentative_g_score < g_score.get(&neighbor) {
+                g_score.set(&neighbor, tentative_g_score);
+                came_from.set(&neighbor, Some(parent));
+                open_set.push(HeapElement { position: neighbor, f_score: tentative_g_score + heuristic(&neighbor) });
+            }
+        }
+    }
+    Err(Box::new(NoPathFoundError()))
+}
+
+fn build_path(came_from: &Grid3D<Option<Point3D>>, start: &Point3D, end: &Point3D) -> Vec<Point3D> {
+    let mut path = vec![*end];
+    let mut pos = *end;
+    while pos != *start {
+        pos = came_from.get(&pos).unwrap();
+        path.push(pos);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array3;
+
+    use super::*;
+
+    #[test]
+    fn test_neighbors() {
+        let boundaries = ((0, 0, 0), (10, 10, 10));
+        assert_eq!(get_neighbors_3d((5, 5, 5), boundaries).count(), 26);
+        assert_eq!(get_neighbors_3d((0, 0, 0), boundaries).count(), 7);
+        assert_eq!(get_neighbors_3d((0, 5, 9), boundaries).count(), 11);
+    }
+
+    #[test]
+    fn test_line() {
+        let line: Vec<Point3D> = line_3d(&(0, 0, 0), &(4, -2, 1)).collect();
+        assert_eq!(line.len(), 5);
+        assert_eq!(line.first(), Some(&(0, 0, 0)));
+        assert_eq!(line.last(), Some(&(4, -2, 1)));
+        for w in line.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            assert!((a.0 - b.0).abs() <= 1 && (a.1 - b.1).abs() <= 1 && (a.2 - b.2).abs() <= 1);
+        }
+        assert_eq!(line_3d(&(1, 1, 1), &(1, 1, 1)).collect::<Vec<_>>(), vec![(1, 1, 1)]);
+    }
+
+    #[test]
+    fn test_find_path_through_hole() -> Result<(), Box<dyn std::error::Error>> {
+        let mut arr = Array3::from_elem((10, 10, 10), false);
+        for y in 0..10 {
+            for z in 0..10 {
+                arr[(5, y, z)] = !(y == 8 && z == 8);
+            }
+        }
+        let map = VoxelMap::new(Grid3D::from(arr));
+
+        let path = find_path_3d_impl(&map, (0, 0, 0), (9, 0, 0))?;
+        assert_eq!(path.first(), Some(&(0, 0, 0)));
+        assert_eq!(path.last(), Some(&(9, 0, 0)));
+        assert!(path.windows(2).any(|w| line_3d(&w[0], &w[1]).any(|pos| pos == (5, 8, 8))));
+        assert!(path.windows(2).all(|w| map.line_of_sight(&w[0], &w[1])));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_path() {
+        let mut arr = Array3::from_elem((6, 6, 6), false);
+        for y in 0..6 {
+            for z in 0..6 {
+                arr[(3, y, z)] = true;
+            }
+        }
+        let map = VoxelMap::new(Grid3D::from(arr));
+
+        assert!(find_path_3d_impl(&map, (0, 0, 0), (5, 5, 5)).is_err());
+        assert_eq!(find_path_3d_impl(&map, (0, 0, 0), (2, 5, 4)).unwrap(), vec![(0, 0, 0), (2, 5, 4)]);
+    }
+}